@@ -0,0 +1,535 @@
+// acl users and groups management.
+// see: https://dgraph.io/docs/enterprise-features/access-control-lists/
+
+use crate::{
+    dgraph::Dgraph,
+    output::{self, Done, Format, Report, DONE},
+    secret,
+    table::print_table,
+};
+use anyhow::{anyhow, Result};
+use argh::FromArgs;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "user", description = "manage acl users")]
+pub struct User {
+    #[argh(subcommand)]
+    subcommand: UserSubCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum UserSubCommand {
+    Add(UserAdd),
+    Delete(UserDelete),
+    UpdatePassword(UserUpdatePassword),
+    List(UserList),
+    AddToGroup(UserAddToGroup),
+    RemoveFromGroup(UserRemoveFromGroup),
+}
+
+impl User {
+    pub fn exec(self, dgraph: &Dgraph, format: Format) -> Result<i32> {
+        match self.subcommand {
+            UserSubCommand::Add(x) => output::print(format, &x.exec(dgraph)?),
+            UserSubCommand::Delete(x) => output::print(format, &x.exec(dgraph)?),
+            UserSubCommand::UpdatePassword(x) => output::print(format, &x.exec(dgraph)?),
+            UserSubCommand::List(x) => output::print(format, &x.exec(dgraph)?),
+            UserSubCommand::AddToGroup(x) => output::print(format, &x.exec(dgraph)?),
+            UserSubCommand::RemoveFromGroup(x) => output::print(format, &x.exec(dgraph)?),
+        }
+    }
+}
+
+// `required_password` returns password given either with --password or
+// --password-file.
+pub fn required_password(value: Option<String>, file: Option<String>) -> Result<String> {
+    secret::resolve("--password", value, file)?
+        .ok_or_else(|| anyhow!("either --password or --password-file is required"))
+}
+
+#[derive(Deserialize, Debug)]
+struct Name {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct DeletePayload {
+    #[serde(rename = "numUids")]
+    num_uids: u64,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "add", description = "add a user")]
+struct UserAdd {
+    #[argh(positional)]
+    name: String,
+
+    #[argh(option, description = "user's password")]
+    password: Option<String>,
+
+    #[argh(option, description = "file to read user's password from")]
+    password_file: Option<String>,
+
+    #[argh(option, description = "group to add the user to (can be repeated)")]
+    group: Vec<String>,
+}
+impl UserAdd {
+    fn exec(self, dgraph: &Dgraph) -> Result<Done> {
+        let password = required_password(self.password, self.password_file)?;
+        let groups: Vec<JsonValue> = self.group.iter().map(|g| json!({ "name": g })).collect();
+        dgraph.query::<_, JsonValue>(
+            "admin",
+            r#"mutation addUser($input: [AddUserInput!]!) {
+                addUser(input: $input) { user { name } }
+            }"#,
+            json!({
+                "input": [{
+                    "name": &self.name,
+                    "password": password,
+                    "groups": groups,
+                }]
+            }),
+        )?;
+        Ok(DONE)
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "delete", description = "delete a user")]
+struct UserDelete {
+    #[argh(positional)]
+    name: String,
+}
+impl UserDelete {
+    fn exec(self, dgraph: &Dgraph) -> Result<Done> {
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "camelCase")]
+        struct Data {
+            delete_user: DeletePayload,
+        }
+        let resp = dgraph.query::<_, Data>(
+            "admin",
+            r#"mutation deleteUser($name: String!) {
+                deleteUser(filter: { name: { eq: $name } }) { numUids }
+            }"#,
+            json!({ "name": &self.name }),
+        )?;
+        match resp {
+            Some(data) if data.delete_user.num_uids > 0 => Ok(DONE),
+            _ => Err(anyhow!("user {:?} not found", &self.name)),
+        }
+    }
+}
+
+// `update_user` applies `set` and `remove` patches to a user.
+fn update_user(dgraph: &Dgraph, name: &str, set: JsonValue, remove: JsonValue) -> Result<Done> {
+    #[derive(Deserialize, Debug)]
+    struct Payload {
+        user: Option<Vec<Name>>,
+    }
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    struct Data {
+        update_user: Payload,
+    }
+    let resp = dgraph.query::<_, Data>(
+        "admin",
+        r#"mutation updateUser($input: UpdateUserInput!) {
+            updateUser(input: $input) { user { name } }
+        }"#,
+        json!({
+            "input": {
+                "filter": { "name": { "eq": name } },
+                "set": set,
+                "remove": remove,
+            }
+        }),
+    )?;
+    let updated = resp
+        .and_then(|data| data.update_user.user)
+        .unwrap_or_default();
+    if updated.is_empty() {
+        Err(anyhow!("user {:?} not found", name))
+    } else {
+        Ok(DONE)
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "update-password",
+    description = "change user's password"
+)]
+struct UserUpdatePassword {
+    #[argh(positional)]
+    name: String,
+
+    #[argh(option, description = "new password")]
+    password: Option<String>,
+
+    #[argh(option, description = "file to read the new password from")]
+    password_file: Option<String>,
+}
+impl UserUpdatePassword {
+    fn exec(self, dgraph: &Dgraph) -> Result<Done> {
+        let password = required_password(self.password, self.password_file)?;
+        update_user(
+            dgraph,
+            &self.name,
+            json!({ "password": password }),
+            JsonValue::Null,
+        )
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "add-to-group", description = "add user to groups")]
+struct UserAddToGroup {
+    #[argh(positional)]
+    name: String,
+
+    #[argh(positional)]
+    groups: Vec<String>,
+}
+impl UserAddToGroup {
+    fn exec(self, dgraph: &Dgraph) -> Result<Done> {
+        let groups: Vec<JsonValue> = self.groups.iter().map(|g| json!({ "name": g })).collect();
+        update_user(
+            dgraph,
+            &self.name,
+            json!({ "groups": groups }),
+            JsonValue::Null,
+        )
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "remove-from-group",
+    description = "remove user from groups"
+)]
+struct UserRemoveFromGroup {
+    #[argh(positional)]
+    name: String,
+
+    #[argh(positional)]
+    groups: Vec<String>,
+}
+impl UserRemoveFromGroup {
+    fn exec(self, dgraph: &Dgraph) -> Result<Done> {
+        let groups: Vec<JsonValue> = self.groups.iter().map(|g| json!({ "name": g })).collect();
+        update_user(
+            dgraph,
+            &self.name,
+            JsonValue::Null,
+            json!({ "groups": groups }),
+        )
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "list", description = "list users")]
+struct UserList {}
+
+#[derive(Serialize)]
+struct UserInfo {
+    name: String,
+    groups: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+struct Users(Vec<UserInfo>);
+
+impl Report for Users {
+    fn print_text(&self) {
+        let rows: Vec<Vec<String>> = self
+            .0
+            .iter()
+            .map(|u| vec![u.name.clone(), u.groups.join(",")])
+            .collect();
+        print_table(&["USER", "GROUPS"], &rows);
+    }
+}
+
+impl UserList {
+    fn exec(self, dgraph: &Dgraph) -> Result<Users> {
+        #[derive(Deserialize, Debug)]
+        struct UserPayload {
+            name: String,
+            groups: Option<Vec<Name>>,
+        }
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "camelCase")]
+        struct Data {
+            query_user: Option<Vec<UserPayload>>,
+        }
+        let resp = dgraph.query::<_, Data>(
+            "admin",
+            r#"query queryUser {
+                queryUser { name groups { name } }
+            }"#,
+            (),
+        )?;
+        let users = resp.and_then(|data| data.query_user).unwrap_or_default();
+        Ok(Users(
+            users
+                .into_iter()
+                .map(|u| UserInfo {
+                    name: u.name,
+                    groups: u.groups.into_iter().flatten().map(|g| g.name).collect(),
+                })
+                .collect(),
+        ))
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "group", description = "manage acl groups")]
+pub struct Group {
+    #[argh(subcommand)]
+    subcommand: GroupSubCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum GroupSubCommand {
+    Add(GroupAdd),
+    Delete(GroupDelete),
+    List(GroupList),
+    SetRule(GroupSetRule),
+}
+
+impl Group {
+    pub fn exec(self, dgraph: &Dgraph, format: Format) -> Result<i32> {
+        match self.subcommand {
+            GroupSubCommand::Add(x) => output::print(format, &x.exec(dgraph)?),
+            GroupSubCommand::Delete(x) => output::print(format, &x.exec(dgraph)?),
+            GroupSubCommand::List(x) => output::print(format, &x.exec(dgraph)?),
+            GroupSubCommand::SetRule(x) => output::print(format, &x.exec(dgraph)?),
+        }
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "add", description = "add a group")]
+struct GroupAdd {
+    #[argh(positional)]
+    name: String,
+}
+impl GroupAdd {
+    fn exec(self, dgraph: &Dgraph) -> Result<Done> {
+        dgraph.query::<_, JsonValue>(
+            "admin",
+            r#"mutation addGroup($input: [AddGroupInput!]!) {
+                addGroup(input: $input) { group { name } }
+            }"#,
+            json!({ "input": [{ "name": &self.name }] }),
+        )?;
+        Ok(DONE)
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "delete", description = "delete a group")]
+struct GroupDelete {
+    #[argh(positional)]
+    name: String,
+}
+impl GroupDelete {
+    fn exec(self, dgraph: &Dgraph) -> Result<Done> {
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "camelCase")]
+        struct Data {
+            delete_group: DeletePayload,
+        }
+        let resp = dgraph.query::<_, Data>(
+            "admin",
+            r#"mutation deleteGroup($name: String!) {
+                deleteGroup(filter: { name: { eq: $name } }) { numUids }
+            }"#,
+            json!({ "name": &self.name }),
+        )?;
+        match resp {
+            Some(data) if data.delete_group.num_uids > 0 => Ok(DONE),
+            _ => Err(anyhow!("group {:?} not found", &self.name)),
+        }
+    }
+}
+
+// permission bits, see:
+// https://dgraph.io/docs/enterprise-features/access-control-lists/#assign-predicate-permissions-to-groups
+const READ: u8 = 4;
+const WRITE: u8 = 2;
+const MODIFY: u8 = 1;
+
+// `parse_permission` accepts either permission bits (0-7) or a combination
+// of `r`, `w` and `m` letters.
+fn parse_permission(s: &str) -> Result<u8, String> {
+    if let Ok(bits) = s.parse::<u8>() {
+        return if bits <= READ | WRITE | MODIFY {
+            Ok(bits)
+        } else {
+            Err(format!("permission must be between 0 and 7, got {}", bits))
+        };
+    }
+    s.chars().try_fold(0, |bits, c| match c {
+        'r' => Ok(bits | READ),
+        'w' => Ok(bits | WRITE),
+        'm' => Ok(bits | MODIFY),
+        '-' => Ok(bits),
+        _ => Err(format!(
+            "permission must be bits (0-7) or a combination of r, w and m, got {:?}",
+            s
+        )),
+    })
+}
+
+fn format_permission(bits: u8) -> String {
+    [(READ, 'r'), (WRITE, 'w'), (MODIFY, 'm')]
+        .iter()
+        .map(|(bit, c)| if bits & bit != 0 { *c } else { '-' })
+        .collect()
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "set-rule",
+    description = "set group's permission on a predicate"
+)]
+struct GroupSetRule {
+    #[argh(positional)]
+    name: String,
+
+    #[argh(option, description = "predicate the rule applies to")]
+    predicate: String,
+
+    #[argh(
+        option,
+        from_str_fn(parse_permission),
+        description = "permission bits (read=4, write=2, modify=1) or letters, like rw"
+    )]
+    permission: u8,
+}
+impl GroupSetRule {
+    fn exec(self, dgraph: &Dgraph) -> Result<Done> {
+        #[derive(Deserialize, Debug)]
+        struct Payload {
+            group: Option<Vec<Name>>,
+        }
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "camelCase")]
+        struct Data {
+            update_group: Payload,
+        }
+        let resp = dgraph.query::<_, Data>(
+            "admin",
+            r#"mutation updateGroup($input: UpdateGroupInput!) {
+                updateGroup(input: $input) { group { name } }
+            }"#,
+            json!({
+                "input": {
+                    "filter": { "name": { "eq": &self.name } },
+                    "set": {
+                        "rules": [{
+                            "predicate": &self.predicate,
+                            "permission": self.permission,
+                        }]
+                    },
+                }
+            }),
+        )?;
+        let updated = resp
+            .and_then(|data| data.update_group.group)
+            .unwrap_or_default();
+        if updated.is_empty() {
+            Err(anyhow!("group {:?} not found", &self.name))
+        } else {
+            Ok(DONE)
+        }
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "list", description = "list groups")]
+struct GroupList {}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct Rule {
+    predicate: String,
+    // permission bits
+    permission: u8,
+}
+
+#[derive(Serialize)]
+struct GroupInfo {
+    name: String,
+    users: Vec<String>,
+    rules: Vec<Rule>,
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+struct Groups(Vec<GroupInfo>);
+
+impl Report for Groups {
+    fn print_text(&self) {
+        let rows: Vec<Vec<String>> = self
+            .0
+            .iter()
+            .map(|g| {
+                let rules: Vec<String> = g
+                    .rules
+                    .iter()
+                    .map(|r| format!("{}:{}", r.predicate, format_permission(r.permission)))
+                    .collect();
+                vec![g.name.clone(), g.users.join(","), rules.join(" ")]
+            })
+            .collect();
+        print_table(&["GROUP", "USERS", "RULES"], &rows);
+    }
+}
+
+impl GroupList {
+    fn exec(self, dgraph: &Dgraph) -> Result<Groups> {
+        #[derive(Deserialize, Debug)]
+        struct GroupPayload {
+            name: String,
+            users: Option<Vec<Name>>,
+            rules: Option<Vec<Rule>>,
+        }
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "camelCase")]
+        struct Data {
+            query_group: Option<Vec<GroupPayload>>,
+        }
+        let resp = dgraph.query::<_, Data>(
+            "admin",
+            r#"query queryGroup {
+                queryGroup {
+                    name
+                    users { name }
+                    rules { predicate permission }
+                }
+            }"#,
+            (),
+        )?;
+        let groups = resp.and_then(|data| data.query_group).unwrap_or_default();
+        Ok(Groups(
+            groups
+                .into_iter()
+                .map(|g| GroupInfo {
+                    name: g.name,
+                    users: g.users.into_iter().flatten().map(|u| u.name).collect(),
+                    rules: g.rules.unwrap_or_default(),
+                })
+                .collect(),
+        ))
+    }
+}
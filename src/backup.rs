@@ -0,0 +1,602 @@
+// progress of backups and restores is printed to stderr, so that stdout has
+// only the report.
+
+use crate::{
+    dgraph::{Dgraph, Response},
+    health::Node,
+    output::Report,
+    table::print_table,
+};
+use anyhow::{anyhow, Result};
+use argh::FromArgs;
+use humantime::format_duration;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+// how long a task may stay `Unknown` before giving up on it. alphas report
+// `Unknown` for tasks they don't know about, which is either a task that is
+// not registered yet or one that is gone, like after a restart.
+const UNKNOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "backup",
+    description = "create a backup and wait for it to finish"
+)]
+pub struct Backup {
+    #[argh(
+        option,
+        description = "where to store the backup, a path on alpha or s3/minio url"
+    )]
+    destination: String,
+
+    #[argh(
+        switch,
+        description = "create a full backup even if there is a previous one"
+    )]
+    force_full: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BackupPayload {
+    response: Option<Response>,
+    task_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BackupData {
+    backup: BackupPayload,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupDone {
+    message: Option<String>,
+    // older versions of dgraph run backups synchronously, without a task
+    task_id: Option<String>,
+}
+
+impl Report for BackupDone {
+    fn print_text(&self) {
+        // everything has been printed while waiting
+    }
+}
+
+impl Backup {
+    pub fn exec(self, dgraph: &Dgraph) -> Result<BackupDone> {
+        // older versions of dgraph fail the whole mutation if asked for
+        // `taskId`
+        let task_id = if dgraph
+            .type_fields("BackupPayload")?
+            .iter()
+            .any(|f| f == "taskId")
+        {
+            "taskId"
+        } else {
+            ""
+        };
+        let resp = dgraph.query::<_, BackupData>(
+            "admin",
+            &format!(
+                r#"mutation backup($input: BackupInput!) {{
+                    backup(input: $input) {{
+                        response {{ message }}
+                        {}
+                    }}
+                }}"#,
+                task_id
+            ),
+            json!({
+                "input": {
+                    "destination": &self.destination,
+                    "forceFull": self.force_full,
+                }
+            }),
+        )?;
+        let backup = resp.ok_or_else(|| anyhow!("empty response"))?.backup;
+        let message = backup.response.map(|r| r.message);
+        if let Some(message) = &message {
+            eprintln!("{}", message);
+        }
+        if let Some(task_id) = &backup.task_id {
+            wait_for_task(dgraph, task_id)?;
+        }
+        Ok(BackupDone {
+            message,
+            task_id: backup.task_id,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Task {
+    status: String,
+    last_updated: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TaskData {
+    task: Task,
+}
+
+// `wait_for_task` polls the `task` query until the task either succeeds or
+// fails, printing its status whenever it changes.
+fn wait_for_task(dgraph: &Dgraph, task_id: &str) -> Result<()> {
+    eprintln!("task id: {}", task_id);
+    let started = Instant::now();
+    let mut last_status = String::new();
+    let mut unknown_since = None;
+    loop {
+        let resp = dgraph.query::<_, TaskData>(
+            "admin",
+            r#"query task($id: String!) {
+                task(input: { id: $id }) { status lastUpdated }
+            }"#,
+            json!({ "id": task_id }),
+        )?;
+        let task = resp.ok_or_else(|| anyhow!("empty response"))?.task;
+        if task.status != last_status {
+            eprintln!(
+                "[{}] {}{}",
+                format_duration(Duration::from_secs(started.elapsed().as_secs())),
+                &task.status,
+                task.last_updated
+                    .as_ref()
+                    .map(|t| format!(" (last updated: {})", t))
+                    .unwrap_or_default(),
+            );
+            last_status = task.status.clone();
+        }
+        match task.status.as_str() {
+            "Success" => return Ok(()),
+            "Failed" => return Err(anyhow!("task {} failed, see alpha logs", task_id)),
+            "Unknown" => {
+                let since = *unknown_since.get_or_insert_with(Instant::now);
+                if since.elapsed() >= UNKNOWN_TIMEOUT {
+                    return Err(anyhow!(
+                        "task {} is still unknown to alpha after {}, see alpha logs",
+                        task_id,
+                        format_duration(UNKNOWN_TIMEOUT)
+                    ));
+                }
+            }
+            // Queued or Running
+            _ => unknown_since = None,
+        }
+        sleep(POLL_INTERVAL);
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "restore",
+    description = "restore a backup and wait for it to finish"
+)]
+pub struct Restore {
+    #[argh(
+        option,
+        description = "where the backup is stored, a path on alpha or s3/minio url"
+    )]
+    location: String,
+
+    #[argh(
+        option,
+        description = "id of the backup series to restore (default: the latest)"
+    )]
+    backup_id: Option<String>,
+
+    #[argh(
+        option,
+        description = "restore up to this backup number within the series (default: the latest)"
+    )]
+    backup_num: Option<u64>,
+
+    #[argh(
+        option,
+        description = "path on alpha to the key the backup was encrypted with"
+    )]
+    encryption_key_file: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RestoreData {
+    restore: RestorePayload,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RestorePayload {
+    code: String,
+    message: String,
+    // only versions with `restoreStatus` have it
+    restore_id: Option<i64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RestoreStatus {
+    // UNKNOWN, IN_PROGRESS, OK or ERR
+    status: String,
+    errors: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RestoreStatusData {
+    restore_status: Option<RestoreStatus>,
+}
+
+// NOTE: versions without `restoreStatus` have no way to tell how a restore
+// went, instead alphas report `opRestore` in the `ongoing` operations of
+// `/health` while restoring.
+const RESTORE_OP: &str = "opRestore";
+// how long to wait for the restore to show up in `/health`, or to become
+// known to `restoreStatus`.
+const RESTORE_GRACE: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+pub struct RestoreDone {
+    message: String,
+    // whether alpha said that the restore succeeded, rather than that it's
+    // no longer running
+    confirmed: bool,
+}
+
+impl Report for RestoreDone {
+    fn print_text(&self) {
+        // everything has been printed while waiting
+    }
+}
+
+// `Progress` is what is known about the restore after a poll.
+#[derive(Debug, PartialEq)]
+enum Progress {
+    Running,
+    Succeeded,
+    Failed(String),
+    // alpha has no status to tell success from failure
+    Unconfirmed(&'static str),
+}
+
+// `progress` decides how the restore is going, from `restoreStatus` if alpha
+// has it, or from `/health` otherwise: whether it has `seen` the restore
+// running before, and whether it's `restoring` now.
+fn progress(
+    status: Option<&RestoreStatus>,
+    seen: bool,
+    restoring: bool,
+    elapsed: Duration,
+) -> Progress {
+    if let Some(status) = status {
+        return match status.status.as_str() {
+            "OK" => Progress::Succeeded,
+            "ERR" => Progress::Failed(match &status.errors {
+                Some(errors) if !errors.is_empty() => errors.join(", "),
+                _ => String::from("see alpha logs"),
+            }),
+            "UNKNOWN" if elapsed >= RESTORE_GRACE => {
+                Progress::Failed(String::from("alpha doesn't know about the restore"))
+            }
+            _ => Progress::Running,
+        };
+    }
+    if restoring {
+        Progress::Running
+    } else if seen {
+        Progress::Unconfirmed("restore is no longer running")
+    } else if elapsed >= RESTORE_GRACE {
+        Progress::Unconfirmed(
+            "restore didn't show up in /health, it either finished or didn't start",
+        )
+    } else {
+        Progress::Running
+    }
+}
+
+impl Restore {
+    pub fn exec(self, dgraph: &Dgraph) -> Result<RestoreDone> {
+        // asking for `restoreId` fails the mutation on versions without it
+        let has_status = dgraph
+            .type_fields("RestorePayload")?
+            .iter()
+            .any(|f| f == "restoreId")
+            && dgraph
+                .type_fields("Query")?
+                .iter()
+                .any(|f| f == "restoreStatus");
+        let resp = dgraph.query::<_, RestoreData>(
+            "admin",
+            if has_status {
+                r#"mutation restore($input: RestoreInput!) {
+                    restore(input: $input) { code message restoreId }
+                }"#
+            } else {
+                r#"mutation restore($input: RestoreInput!) {
+                    restore(input: $input) { code message }
+                }"#
+            },
+            json!({
+                "input": {
+                    "location": &self.location,
+                    "backupId": &self.backup_id,
+                    "backupNum": &self.backup_num,
+                    "encryptionKeyFile": &self.encryption_key_file,
+                }
+            }),
+        )?;
+        let restore = resp.ok_or_else(|| anyhow!("empty response"))?.restore;
+        eprintln!("{}", &restore.message);
+        if restore.code != "Success" {
+            return Err(anyhow!("restore failed: {}", &restore.code));
+        }
+
+        let started = Instant::now();
+        let mut seen = false;
+        let mut last_status = String::new();
+        loop {
+            let elapsed = Duration::from_secs(started.elapsed().as_secs());
+            let (status, restoring) = match restore.restore_id {
+                Some(id) => {
+                    let status = dgraph
+                        .query::<_, RestoreStatusData>(
+                            "admin",
+                            r#"query restoreStatus($id: Int!) {
+                                restoreStatus(restoreId: $id) { status errors }
+                            }"#,
+                            json!({ "id": id }),
+                        )?
+                        .and_then(|data| data.restore_status)
+                        .ok_or_else(|| anyhow!("empty response"))?;
+                    if status.status != last_status {
+                        eprintln!("[{}] {}", format_duration(elapsed), &status.status);
+                        last_status = status.status.clone();
+                    }
+                    (Some(status), false)
+                }
+                None => {
+                    let nodes: Vec<Node> = dgraph.get("health?all")?;
+                    let restoring = nodes.iter().filter(|n| is_restoring(n)).count();
+                    if restoring > 0 {
+                        eprintln!(
+                            "[{}] restoring on {}/{} nodes",
+                            format_duration(elapsed),
+                            restoring,
+                            nodes.len()
+                        );
+                    }
+                    (None, restoring > 0)
+                }
+            };
+            match progress(status.as_ref(), seen, restoring, elapsed) {
+                Progress::Running => seen |= restoring,
+                Progress::Succeeded => {
+                    eprintln!("[{}] done", format_duration(elapsed));
+                    return Ok(RestoreDone {
+                        message: restore.message,
+                        confirmed: true,
+                    });
+                }
+                Progress::Failed(reason) => return Err(anyhow!("restore failed: {}", reason)),
+                Progress::Unconfirmed(reason) => {
+                    eprintln!(
+                        "[{}] {}, whether it succeeded can't be told, see alpha logs",
+                        format_duration(elapsed),
+                        reason
+                    );
+                    return Ok(RestoreDone {
+                        message: restore.message,
+                        confirmed: false,
+                    });
+                }
+            }
+            sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+fn is_restoring(node: &Node) -> bool {
+    node.ongoing.iter().any(|op| op == RESTORE_OP)
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "list-backups",
+    description = "list backups stored in a location"
+)]
+pub struct ListBackups {
+    #[argh(
+        option,
+        description = "where backups are stored, a path on alpha or s3/minio url"
+    )]
+    location: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BackupGroup {
+    group_id: u32,
+    predicates: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    backup_id: String,
+    backup_num: u64,
+    #[serde(rename = "type")]
+    kind: String,
+    path: String,
+    since: u64,
+    encrypted: bool,
+    groups: Option<Vec<BackupGroup>>,
+}
+
+impl Manifest {
+    // backups are stored in directories named `dgraph.20210517.095641.969`,
+    // which is the only place that has the time when backup was taken.
+    fn timestamp(&self) -> String {
+        let dir = self.path.rsplit('/').next().unwrap_or_default();
+        let parts: Vec<&str> = dir.split('.').collect();
+        match parts.as_slice() {
+            ["dgraph", date, time, ..] if date.len() == 8 && time.len() == 6 => format!(
+                "{}-{}-{} {}:{}:{}",
+                &date[0..4],
+                &date[4..6],
+                &date[6..8],
+                &time[0..2],
+                &time[2..4],
+                &time[4..6]
+            ),
+            _ => "-".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ListBackupsData {
+    list_backups: Option<Vec<Manifest>>,
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct Backups {
+    manifests: Vec<Manifest>,
+}
+
+impl Report for Backups {
+    fn print_text(&self) {
+        if self.manifests.is_empty() {
+            println!("no backups");
+            return;
+        }
+        let rows: Vec<Vec<String>> = self
+            .manifests
+            .iter()
+            .map(|m| {
+                let groups: Vec<String> = m
+                    .groups
+                    .iter()
+                    .flatten()
+                    .map(|g| g.group_id.to_string())
+                    .collect();
+                vec![
+                    m.backup_id.clone(),
+                    m.backup_num.to_string(),
+                    m.kind.clone(),
+                    m.timestamp(),
+                    m.since.to_string(),
+                    groups.join(","),
+                    m.encrypted.to_string(),
+                ]
+            })
+            .collect();
+        print_table(
+            &[
+                "BACKUP ID",
+                "NUM",
+                "TYPE",
+                "TIMESTAMP",
+                "READ TS",
+                "GROUPS",
+                "ENCRYPTED",
+            ],
+            &rows,
+        );
+    }
+}
+
+impl ListBackups {
+    pub fn exec(self, dgraph: &Dgraph) -> Result<Backups> {
+        let resp = dgraph.query::<_, ListBackupsData>(
+            "admin",
+            r#"query listBackups($input: ListBackupsInput!) {
+                listBackups(input: $input) {
+                    backupId
+                    backupNum
+                    type
+                    path
+                    since
+                    encrypted
+                    groups { groupId predicates }
+                }
+            }"#,
+            json!({ "input": { "location": &self.location } }),
+        )?;
+        let mut manifests = resp.and_then(|data| data.list_backups).unwrap_or_default();
+        // group backups by series, each series starts with a full backup
+        manifests.sort_by(|a, b| (&a.backup_id, a.backup_num).cmp(&(&b.backup_id, b.backup_num)));
+        Ok(Backups { manifests })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(status: &str, errors: &[&str]) -> RestoreStatus {
+        RestoreStatus {
+            status: status.to_string(),
+            errors: Some(errors.iter().map(|e| e.to_string()).collect()),
+        }
+    }
+
+    #[test]
+    fn progress_from_restore_status() {
+        let now = Duration::from_secs(0);
+        let later = RESTORE_GRACE;
+        assert_eq!(
+            progress(Some(&status("OK", &[])), false, false, now),
+            Progress::Succeeded
+        );
+        assert_eq!(
+            progress(Some(&status("ERR", &["a", "b"])), false, false, now),
+            Progress::Failed(String::from("a, b"))
+        );
+        assert_eq!(
+            progress(Some(&status("ERR", &[])), false, false, now),
+            Progress::Failed(String::from("see alpha logs"))
+        );
+        assert_eq!(
+            progress(Some(&status("IN_PROGRESS", &[])), false, false, later),
+            Progress::Running
+        );
+        // not registered yet, or gone
+        assert_eq!(
+            progress(Some(&status("UNKNOWN", &[])), false, false, now),
+            Progress::Running
+        );
+        assert!(matches!(
+            progress(Some(&status("UNKNOWN", &[])), false, false, later),
+            Progress::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn progress_from_health() {
+        let now = Duration::from_secs(0);
+        let later = RESTORE_GRACE;
+        assert_eq!(progress(None, false, true, later), Progress::Running);
+        assert_eq!(progress(None, true, true, later), Progress::Running);
+        // hasn't shown up yet
+        assert_eq!(progress(None, false, false, now), Progress::Running);
+        // neither success nor failure can be told from `/health`
+        assert!(matches!(
+            progress(None, true, false, now),
+            Progress::Unconfirmed(_)
+        ));
+        assert!(matches!(
+            progress(None, false, false, later),
+            Progress::Unconfirmed(_)
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+// operations on the cluster's membership, that go to zero directly.
+// see: https://dgraph.io/docs/deploy/dgraph-zero/#endpoints
+
+use crate::{
+    confirm,
+    dgraph::Dgraph,
+    output::Report,
+    zero::{parse_lease, Assigned, Lease, Zero, DEFAULT_URL},
+};
+use anyhow::Result;
+use argh::FromArgs;
+use serde::Serialize;
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "remove-node",
+    description = "remove a dead node from its group for good"
+)]
+pub struct RemoveNode {
+    #[argh(option, description = "raft id of the node, see cluster-state")]
+    id: u64,
+
+    #[argh(option, description = "group of the node, 0 for zeros")]
+    group: u64,
+
+    #[argh(
+        option,
+        default = "String::from(DEFAULT_URL)",
+        description = "zero's http url (default: localhost:6080)"
+    )]
+    zero_url: String,
+
+    #[argh(switch, description = "do not ask for confirmation")]
+    yes: bool,
+}
+
+#[derive(Serialize)]
+pub struct NodeRemoved {
+    message: String,
+}
+
+impl Report for NodeRemoved {
+    fn print_text(&self) {
+        println!("{}", &self.message);
+    }
+}
+
+impl RemoveNode {
+    pub fn exec(self, dgraph: &Dgraph) -> Result<NodeRemoved> {
+        let zero = Zero::new(dgraph, &self.zero_url)?;
+        confirm(
+            zero.client(),
+            &format!(
+                "remove node {} from group {}, it will not be able to rejoin",
+                self.id, self.group
+            ),
+            self.yes,
+        )?;
+        Ok(NodeRemoved {
+            message: zero.remove_node(self.id, self.group)?,
+        })
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "assign",
+    description = "lease uids, timestamps or namespace ids, so that they are never handed out"
+)]
+pub struct Assign {
+    #[argh(
+        positional,
+        from_str_fn(parse_lease),
+        description = "uids, timestamps or nsids"
+    )]
+    what: Lease,
+
+    #[argh(positional, description = "how many to lease")]
+    count: u64,
+
+    #[argh(
+        option,
+        default = "String::from(DEFAULT_URL)",
+        description = "zero's http url (default: localhost:6080)"
+    )]
+    zero_url: String,
+}
+
+impl Assign {
+    pub fn exec(self, dgraph: &Dgraph) -> Result<Assigned> {
+        Zero::new(dgraph, &self.zero_url)?.assign(self.what, self.count)
+    }
+}
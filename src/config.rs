@@ -0,0 +1,323 @@
+// config file with named connection profiles, for example:
+//
+//     [profiles.staging]
+//     url = "https://staging.cloud.dgraph.io"
+//     auth_file = "/run/secrets/dgraph"
+//     protected = true
+//
+// it's a small subset of toml: tables, strings, integers and booleans.
+
+use crate::secret;
+use anyhow::{anyhow, Context, Result};
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+#[derive(Debug, Default)]
+pub struct Profile {
+    pub url: Option<String>,
+    pub auth: Option<String>,
+    pub zero_auth: Option<String>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub namespace: Option<u64>,
+    // protected profiles always ask for confirmation, even with `--yes`
+    pub protected: bool,
+    pub tls_ca: Option<String>,
+    pub tls_cert: Option<String>,
+    pub tls_key: Option<String>,
+    pub tls_server_name: Option<String>,
+}
+
+#[derive(Debug)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+impl Value {
+    fn string(&self, key: &str) -> Result<String> {
+        match self {
+            Value::String(s) => Ok(s.clone()),
+            _ => Err(anyhow!("{} must be a string", key)),
+        }
+    }
+
+    fn unsigned(&self, key: &str) -> Result<u64> {
+        match self {
+            Value::Integer(n) if *n >= 0 => Ok(*n as u64),
+            _ => Err(anyhow!("{} must be a non-negative integer", key)),
+        }
+    }
+
+    fn boolean(&self, key: &str) -> Result<bool> {
+        match self {
+            Value::Boolean(b) => Ok(*b),
+            _ => Err(anyhow!("{} must be true or false", key)),
+        }
+    }
+}
+
+// `path` returns `$XDG_CONFIG_HOME/dgraph-admin/config.toml`, or
+// `~/.config/dgraph-admin/config.toml` if `XDG_CONFIG_HOME` is not set.
+fn path() -> Result<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let home = env::var_os("HOME").ok_or_else(|| anyhow!("HOME is not set"))?;
+            PathBuf::from(home).join(".config")
+        }
+    };
+    Ok(config_dir.join("dgraph-admin").join("config.toml"))
+}
+
+// `load_profile` reads the config file and returns the profile with the
+// given name.
+pub fn load_profile(name: &str) -> Result<Profile> {
+    let path = path()?;
+    let src =
+        fs::read_to_string(&path).with_context(|| format!("could not read {}", path.display()))?;
+    let tables = parse(&src).with_context(|| format!("could not parse {}", path.display()))?;
+    let table = tables
+        .get(&format!("profiles.{}", name))
+        .ok_or_else(|| anyhow!("profile {:?} not found in {}", name, path.display()))?;
+
+    let mut profile = Profile::default();
+    for (key, value) in table {
+        let key = key.as_str();
+        match key {
+            "url" => profile.url = Some(value.string(key)?),
+            "auth" => profile.auth = Some(value.string(key)?),
+            "auth_file" => profile.auth = Some(secret::read_file(&value.string(key)?)?),
+            "zero_auth" => profile.zero_auth = Some(value.string(key)?),
+            "zero_auth_file" => profile.zero_auth = Some(secret::read_file(&value.string(key)?)?),
+            "user" => profile.user = Some(value.string(key)?),
+            "password" => profile.password = Some(value.string(key)?),
+            "password_file" => profile.password = Some(secret::read_file(&value.string(key)?)?),
+            "namespace" => profile.namespace = Some(value.unsigned(key)?),
+            "protected" => profile.protected = value.boolean(key)?,
+            "tls_ca" => profile.tls_ca = Some(value.string(key)?),
+            "tls_cert" => profile.tls_cert = Some(value.string(key)?),
+            "tls_key" => profile.tls_key = Some(value.string(key)?),
+            "tls_server_name" => profile.tls_server_name = Some(value.string(key)?),
+            _ => return Err(anyhow!("profile {:?}: unknown key {}", name, key)),
+        }
+    }
+    Ok(profile)
+}
+
+type Table = BTreeMap<String, Value>;
+
+// `parse` returns tables by their dotted names, keys outside of any table go
+// to the "" table.
+fn parse(src: &str) -> Result<BTreeMap<String, Table>> {
+    let mut tables: BTreeMap<String, Table> = BTreeMap::new();
+    let mut current = String::new();
+    for (i, line) in src.lines().enumerate() {
+        let lineno = i + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header
+                .strip_suffix(']')
+                .ok_or_else(|| anyhow!("line {}: expected `]`", lineno))?;
+            current = parse_table_name(header)
+                .ok_or_else(|| anyhow!("line {}: invalid table name", lineno))?;
+            if tables.insert(current.clone(), Table::new()).is_some() {
+                return Err(anyhow!("line {}: table {} defined twice", lineno, current));
+            }
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("line {}: expected `key = value`", lineno))?;
+        let key = key.trim().to_string();
+        let value =
+            parse_value(value.trim()).ok_or_else(|| anyhow!("line {}: invalid value", lineno))?;
+        let table = tables.entry(current.clone()).or_default();
+        if table.contains_key(&key) {
+            return Err(anyhow!("line {}: key {} defined twice", lineno, key));
+        }
+        table.insert(key, value);
+    }
+    Ok(tables)
+}
+
+// `strip_comment` cuts off `# ...`, unless `#` is inside of a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+// `parse_table_name` turns `profiles."my profile"` into `profiles.my profile`.
+fn parse_table_name(header: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    let mut rest = header.trim();
+    while !rest.is_empty() {
+        let part;
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"')?;
+            part = quoted[..end].to_string();
+            rest = quoted[end + 1..].trim_start();
+        } else {
+            let end = rest.find('.').unwrap_or(rest.len());
+            part = rest[..end].trim().to_string();
+            if part.is_empty() {
+                return None;
+            }
+            rest = &rest[end..];
+        }
+        parts.push(part);
+        match rest.strip_prefix('.') {
+            // `profiles.` is missing the last part
+            Some(r) if r.trim().is_empty() => return None,
+            Some(r) => rest = r.trim_start(),
+            None if rest.is_empty() => {}
+            None => return None,
+        }
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("."))
+    }
+}
+
+fn parse_value(s: &str) -> Option<Value> {
+    if let Some(literal) = s.strip_prefix('\'') {
+        return Some(Value::String(literal.strip_suffix('\'')?.to_string()));
+    }
+    if s.starts_with('"') {
+        // toml basic strings use the same escapes as json
+        return serde_json::from_str(s).ok().map(Value::String);
+    }
+    match s {
+        "true" => return Some(Value::Boolean(true)),
+        "false" => return Some(Value::Boolean(false)),
+        _ => {}
+    }
+    s.replace('_', "").parse().ok().map(Value::Integer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(src: &str) -> String {
+        parse(src).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_tables_and_values() {
+        let tables = parse(
+            r#"
+            # a comment
+            top = 1
+
+            [profiles.staging]
+            url = "https://staging.example.com" # a comment
+            auth = 'X-Auth-Token: abc#def'
+            namespace = 1_000
+            protected = true
+
+            [ profiles . "my profile" ]
+            password = "with \"quotes\" and # not a comment"
+            protected = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            tables.keys().collect::<Vec<_>>(),
+            vec!["", "profiles.my profile", "profiles.staging"]
+        );
+        let staging = &tables["profiles.staging"];
+        assert_eq!(
+            staging["url"].string("url").unwrap(),
+            "https://staging.example.com"
+        );
+        assert_eq!(
+            staging["auth"].string("auth").unwrap(),
+            "X-Auth-Token: abc#def"
+        );
+        assert_eq!(staging["namespace"].unsigned("namespace").unwrap(), 1000);
+        assert!(staging["protected"].boolean("protected").unwrap());
+        let mine = &tables["profiles.my profile"];
+        assert_eq!(
+            mine["password"].string("password").unwrap(),
+            r#"with "quotes" and # not a comment"#
+        );
+        assert!(!mine["protected"].boolean("protected").unwrap());
+        assert_eq!(tables[""]["top"].unsigned("top").unwrap(), 1);
+    }
+
+    #[test]
+    fn strips_comments_outside_of_strings() {
+        assert_eq!(strip_comment("a = 1 # comment"), "a = 1 ");
+        assert_eq!(strip_comment(r##"a = "#" # comment"##), r##"a = "#" "##);
+        assert_eq!(strip_comment("a = '#' # comment"), "a = '#' ");
+        assert_eq!(strip_comment(r##"a = "\"#" # c"##), r##"a = "\"#" "##);
+        assert_eq!(strip_comment(r#"a = "\\" # c"#), r#"a = "\\" "#);
+        // backslashes don't escape anything in literal strings
+        assert_eq!(strip_comment(r"a = '\' # c"), r"a = '\' ");
+        assert_eq!(strip_comment("# only a comment"), "");
+    }
+
+    #[test]
+    fn table_names() {
+        assert_eq!(parse_table_name("profiles.a").unwrap(), "profiles.a");
+        assert_eq!(parse_table_name(" profiles . a ").unwrap(), "profiles.a");
+        assert_eq!(
+            parse_table_name(r#"profiles."my.profile""#).unwrap(),
+            "profiles.my.profile"
+        );
+        assert_eq!(
+            parse_table_name(r#""profiles" . "a b""#).unwrap(),
+            "profiles.a b"
+        );
+        assert_eq!(parse_table_name(""), None);
+        assert_eq!(parse_table_name("profiles."), None);
+        assert_eq!(parse_table_name(".a"), None);
+        assert_eq!(parse_table_name("profiles..a"), None);
+        assert_eq!(parse_table_name(r#"profiles."a"b"#), None);
+        assert_eq!(parse_table_name(r#"profiles."a"#), None);
+    }
+
+    #[test]
+    fn rejects_duplicates() {
+        assert_eq!(
+            error("[profiles.a]\nurl = 'x'\n\n[profiles.a]\n"),
+            "line 4: table profiles.a defined twice"
+        );
+        assert_eq!(
+            error("[profiles.a]\nurl = 'x'\n# again\nurl = 'y'\n"),
+            "line 4: key url defined twice"
+        );
+        // the same key in different tables is fine
+        assert!(parse("[a]\nurl = 'x'\n[b]\nurl = 'y'\n").is_ok());
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        assert_eq!(error("\n[profiles.a\n"), "line 2: expected `]`");
+        assert_eq!(error("[a]\n\n[a..b]"), "line 3: invalid table name");
+        assert_eq!(error("[a]\nurl\n"), "line 2: expected `key = value`");
+        assert_eq!(error("[a]\n# url\nurl = x"), "line 3: invalid value");
+        assert_eq!(error("url = \"x"), "line 1: invalid value");
+        assert_eq!(error("url = 'x"), "line 1: invalid value");
+    }
+}
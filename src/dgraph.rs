@@ -0,0 +1,1008 @@
+use crate::output::Report;
+use crate::tls::{self, TlsOptions};
+use anyhow::{anyhow, Context, Result};
+use humantime::format_duration;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::{cell::RefCell, collections::BTreeMap, fmt, io, rc::Rc, thread, time::Duration};
+use url::Url;
+
+// `Credentials` are used to log in to dgraph with acl enabled.
+// see: https://dgraph.io/docs/enterprise-features/access-control-lists/
+#[derive(Clone)]
+pub struct Credentials {
+    pub user: String,
+    pub password: String,
+    pub namespace: Option<u64>,
+}
+
+#[derive(Default, Clone)]
+pub struct Options {
+    // print mutating requests instead of sending them
+    pub dry_run: bool,
+    // always ask for confirmation before doing something destructive
+    pub protected: bool,
+    pub tls: TlsOptions,
+    // `None` means ureq's defaults
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    // how many times to retry requests that don't change anything
+    pub retries: u32,
+    // auth header for zero, alpha's auth header is never sent to zero
+    pub zero_auth: Option<String>,
+}
+
+// delay before the first retry, doubled after every attempt
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(8);
+
+// request body, with an optional content type
+#[derive(Clone, Copy)]
+struct Body<'b> {
+    content_type: Option<&'b str>,
+    data: &'b str,
+}
+
+impl<'b> Body<'b> {
+    fn json(data: &'b str) -> Self {
+        Self {
+            content_type: Some("application/json"),
+            data,
+        }
+    }
+}
+
+// `DryRun` is returned instead of sending a mutating request in dry run mode.
+#[derive(Debug)]
+pub struct DryRun;
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "dry run, request was not sent")
+    }
+}
+
+impl std::error::Error for DryRun {}
+
+// `DryRunRequest` is a request as it would be sent, but with secrets
+// redacted.
+#[derive(Serialize, Clone)]
+pub struct DryRunRequest {
+    method: String,
+    url: String,
+    headers: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+}
+
+// `DryRunReport` is what is reported instead of the command's own report in
+// dry run mode: all of the requests it would have sent.
+#[derive(Serialize)]
+pub struct DryRunReport {
+    pub requests: Vec<DryRunRequest>,
+}
+
+impl Report for DryRunReport {
+    fn print_text(&self) {
+        for (i, request) in self.requests.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("{} {}", &request.method, &request.url);
+            for (key, value) in &request.headers {
+                println!("{}: {}", key, value);
+            }
+            if let Some(body) = &request.body {
+                println!();
+                println!("{}", body);
+            }
+        }
+    }
+}
+
+struct Tokens {
+    access_jwt: String,
+    refresh_jwt: String,
+}
+
+pub struct Dgraph {
+    agent: ureq::Agent,
+    base_url: String,
+    auth_header: Option<String>,
+    credentials: Option<Credentials>,
+    // acquired lazily, on the first request
+    tokens: RefCell<Option<Tokens>>,
+    options: Options,
+    // requests that were not sent in dry run mode, shared with clients for
+    // other nodes
+    dry_run_requests: Rc<RefCell<Vec<DryRunRequest>>>,
+}
+
+// `Response` is the `response { message }` of admin mutations.
+#[derive(Deserialize, Debug)]
+pub struct Response {
+    pub message: String,
+}
+
+#[derive(Serialize, Debug)]
+struct GqlRequest<'q, Variables> {
+    query: &'q str,
+    variables: Variables,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+// spec: https://spec.graphql.org/June2018/#sec-Errors
+#[derive(Deserialize, Serialize, Debug)]
+pub struct GqlError {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locations: Option<Vec<Location>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<Vec<JsonValue>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<JsonValue>,
+}
+
+// `GqlErrors` are errors returned by dgraph, along with the source their
+// locations point to.
+#[derive(Debug)]
+pub struct GqlErrors {
+    pub errors: Vec<GqlError>,
+    // name and text of the source
+    source: Option<(String, String)>,
+}
+
+// spec: https://spec.graphql.org/June2018/#sec-Response-Format
+#[derive(Deserialize, Debug)]
+struct GqlResponse<Data> {
+    data: Option<Data>,
+    errors: Option<Vec<GqlError>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LoginPayload {
+    #[serde(rename = "accessJWT")]
+    access_jwt: String,
+    #[serde(rename = "refreshJWT")]
+    refresh_jwt: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct LoginResponse {
+    response: LoginPayload,
+}
+
+#[derive(Deserialize, Debug)]
+struct LoginData {
+    login: LoginResponse,
+}
+
+impl Dgraph {
+    // `new` constructs a new client for dgraph.
+    // if the given url is missing a scheme (such as "http://" or "https://")
+    // then "http://" will be used.
+    // url must not end with `/graphql`, if it is - it will be trimmed off.
+    pub fn new(
+        url: String,
+        auth_header: Option<String>,
+        credentials: Option<Credentials>,
+        options: Options,
+    ) -> Result<Self> {
+        let mut agent = ureq::AgentBuilder::new();
+        if let Some(timeout) = options.timeout {
+            agent = agent.timeout(timeout);
+        }
+        if let Some(timeout) = options.connect_timeout {
+            agent = agent.timeout_connect(timeout);
+        }
+        if let Some(tls_config) = tls::client_config(&options.tls)? {
+            agent = agent.tls_config(tls_config);
+        }
+
+        Ok(Self {
+            agent: agent.build(),
+            base_url: base_url(&url)?,
+            auth_header,
+            credentials,
+            tokens: RefCell::new(None),
+            options,
+            dry_run_requests: Rc::default(),
+        })
+    }
+
+    // `with_url` returns a client for another node of the cluster, with the
+    // same options, auth header and credentials.
+    pub fn with_url(&self, url: &str) -> Result<Self> {
+        Ok(Self {
+            agent: self.agent.clone(),
+            base_url: base_url(url)?,
+            auth_header: self.auth_header.clone(),
+            credentials: self.credentials.clone(),
+            tokens: RefCell::new(None),
+            options: self.options.clone(),
+            dry_run_requests: self.dry_run_requests.clone(),
+        })
+    }
+
+    // `without_credentials` is for zero, only alphas know about acl and
+    // alpha's auth header, zero gets its own instead.
+    pub fn without_credentials(mut self) -> Self {
+        self.credentials = None;
+        self.auth_header = self.options.zero_auth.clone();
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn host(&self) -> String {
+        Url::parse(&self.base_url)
+            .ok()
+            .and_then(|url| url.host_str().map(|h| h.to_string()))
+            .unwrap_or_default()
+    }
+
+    pub fn namespace(&self) -> u64 {
+        self.credentials
+            .as_ref()
+            .and_then(|c| c.namespace)
+            .unwrap_or_default()
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.options.dry_run
+    }
+
+    // `for_each_request` calls `f` for every item and collects the results,
+    // stopping at the first error. requests are not sent in dry run mode, so
+    // it keeps going past them to show all of the requests, not only the
+    // first one, and returns `DryRun` in the end.
+    pub fn for_each_request<T, R>(
+        &self,
+        items: impl IntoIterator<Item = T>,
+        mut f: impl FnMut(T) -> Result<R>,
+    ) -> Result<Vec<R>> {
+        let mut results = Vec::new();
+        for item in items {
+            match f(item) {
+                Err(err) if err.is::<DryRun>() => continue,
+                result => results.push(result?),
+            }
+        }
+        if self.is_dry_run() {
+            return Err(DryRun.into());
+        }
+        Ok(results)
+    }
+
+    // `dry_run_report` reports the requests that were not sent in dry run
+    // mode, by this client and clients for other nodes.
+    pub fn dry_run_report(&self) -> DryRunReport {
+        DryRunReport {
+            requests: self.dry_run_requests.borrow().clone(),
+        }
+    }
+
+    pub fn is_protected(&self) -> bool {
+        self.options.protected
+    }
+
+    fn add_auth_header(&self, req: ureq::Request) -> ureq::Request {
+        if let Some(ah) = &self.auth_header {
+            if let Some((key, value)) = ah.split_once(':') {
+                return req.set(key, value);
+            }
+        }
+        req
+    }
+
+    // `send_once` sends a request, returning status code and body.
+    // non 2xx responses are not treated as errors here, because dgraph
+    // explains what's wrong in the body.
+    fn send_once(
+        &self,
+        method: &str,
+        endpoint: &str,
+        body: Option<Body>,
+        with_token: bool,
+    ) -> Result<(u16, String)> {
+        let mut req = self
+            .agent
+            .request(method, &(self.base_url.clone() + endpoint));
+        req = self.add_auth_header(req);
+        if with_token {
+            if let Some(tokens) = &*self.tokens.borrow() {
+                req = req.set("X-Dgraph-AccessToken", &tokens.access_jwt);
+            }
+        }
+        let resp = match body {
+            Some(body) => {
+                if let Some(content_type) = body.content_type {
+                    req = req.set("Content-Type", content_type);
+                }
+                req.send_string(body.data)
+            }
+            None => req.call(),
+        };
+        match resp {
+            Ok(resp) => Ok((resp.status(), resp.into_string()?)),
+            Err(ureq::Error::Status(status, resp)) => Ok((status, resp.into_string()?)),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // `send_retrying` is like `send_once`, but retries requests that don't
+    // change anything on connection errors and 5xx responses, so that
+    // restarting alphas can be waited out.
+    fn send_retrying(
+        &self,
+        method: &str,
+        endpoint: &str,
+        body: Option<Body>,
+        mutating: bool,
+        with_token: bool,
+    ) -> Result<(u16, String)> {
+        let mut backoff = RETRY_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            let result = self.send_once(method, endpoint, body, with_token);
+            if mutating || attempt >= self.options.retries {
+                return result;
+            }
+            let reason = match &result {
+                Ok((status, _)) if *status >= 500 => {
+                    format!("{}{}: status code {}", &self.base_url, endpoint, status)
+                }
+                // ureq errors mention the url already
+                Err(err) if is_transient(err) => err.to_string(),
+                _ => return result,
+            };
+            attempt += 1;
+            eprintln!(
+                "{}, retrying in {} ({}/{})",
+                reason,
+                format_duration(backoff),
+                attempt,
+                self.options.retries
+            );
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+        }
+    }
+
+    // `record_request` keeps the request for the dry run report.
+    fn record_request(&self, method: &str, endpoint: &str, body: Option<Body>) {
+        let mut headers = BTreeMap::new();
+        if let Some(content_type) = body.and_then(|b| b.content_type) {
+            headers.insert(String::from("Content-Type"), content_type.to_string());
+        }
+        if let Some((key, _)) = self.auth_header.as_ref().and_then(|ah| ah.split_once(':')) {
+            headers.insert(key.trim().to_string(), String::from("<redacted>"));
+        }
+        // there's no token in dry run mode, logging in is skipped, but it
+        // would be there
+        if self.credentials.is_some() || self.tokens.borrow().is_some() {
+            headers.insert(
+                String::from("X-Dgraph-AccessToken"),
+                String::from("<redacted>"),
+            );
+        }
+        self.dry_run_requests.borrow_mut().push(DryRunRequest {
+            method: method.to_string(),
+            url: format!("{}{}", &self.base_url, endpoint),
+            headers,
+            body: body.map(redact_body),
+        });
+    }
+
+    // `send` sends a request on behalf of the logged in user (if any),
+    // refreshing the access token once if it has expired.
+    // `mutating` requests change something, they are not retried, and are
+    // not sent in dry run mode.
+    fn send(
+        &self,
+        method: &str,
+        endpoint: &str,
+        body: Option<Body>,
+        mutating: bool,
+    ) -> Result<String> {
+        if self.options.dry_run && mutating {
+            self.record_request(method, endpoint, body);
+            return Err(DryRun.into());
+        }
+        if self.credentials.is_some() && self.tokens.borrow().is_none() {
+            self.login()?;
+        }
+        let (mut status, mut resp) = self.send_retrying(method, endpoint, body, mutating, true)?;
+        if self.credentials.is_some() && is_token_expired(&resp) {
+            self.refresh()?;
+            let (s, r) = self.send_retrying(method, endpoint, body, mutating, true)?;
+            status = s;
+            resp = r;
+        }
+        if status >= 400 {
+            return Err(anyhow!(
+                "{}{}: status code {}: {}",
+                &self.base_url,
+                endpoint,
+                status,
+                resp.trim()
+            ));
+        }
+        Ok(resp)
+    }
+
+    fn login_with(&self, variables: serde_json::Value) -> Result<()> {
+        let query = r#"mutation login($userId: String, $password: String, $namespace: Int, $refreshToken: String) {
+                login(userId: $userId, password: $password, namespace: $namespace, refreshToken: $refreshToken) {
+                    response { accessJWT refreshJWT }
+                }
+            }"#;
+        let body = json!(GqlRequest { query, variables });
+        let (_, resp) = self.send_retrying(
+            "POST",
+            "admin",
+            Some(Body::json(&body.to_string())),
+            false,
+            false,
+        )?;
+        let resp: GqlResponse<LoginData> = serde_json::from_str(&resp)?;
+        let login = gql_result(resp, query)?
+            .ok_or_else(|| anyhow!("login: empty response"))?
+            .login
+            .response;
+        *self.tokens.borrow_mut() = Some(Tokens {
+            access_jwt: login.access_jwt,
+            refresh_jwt: login.refresh_jwt,
+        });
+        Ok(())
+    }
+
+    fn login(&self) -> Result<()> {
+        let credentials = self
+            .credentials
+            .as_ref()
+            .ok_or_else(|| anyhow!("no credentials to log in with"))?;
+        self.login_with(json!({
+            "userId": &credentials.user,
+            "password": &credentials.password,
+            "namespace": credentials.namespace.unwrap_or_default(),
+        }))
+        .context("could not log in")
+    }
+
+    // `refresh` exchanges the refresh token for a new pair of tokens, if
+    // the refresh token has expired as well - logs in again.
+    fn refresh(&self) -> Result<()> {
+        let refresh_jwt = self.tokens.borrow().as_ref().map(|t| t.refresh_jwt.clone());
+        match refresh_jwt {
+            Some(refresh_jwt) => self
+                .login_with(json!({ "refreshToken": refresh_jwt }))
+                .or_else(|_| self.login()),
+            None => self.login(),
+        }
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
+        let resp = self.send("GET", endpoint, None, false)?;
+        Ok(serde_json::from_str(&resp)?)
+    }
+
+    // `get_mutating` sends a get request that changes something, like
+    // zero's `/assign`, and returns the response as is.
+    pub fn get_mutating(&self, endpoint: &str) -> Result<String> {
+        self.send("GET", endpoint, None, true)
+    }
+
+    // `post_mutating` sends the data as is, and returns the response as is.
+    pub fn post_mutating(&self, endpoint: &str, data: &str) -> Result<String> {
+        let body = Body {
+            content_type: None,
+            data,
+        };
+        self.send("POST", endpoint, Some(body), true)
+    }
+
+    pub fn query<Variables: Serialize, Data: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &str,
+        variables: Variables,
+    ) -> Result<Option<Data>> {
+        let mutating = query.trim_start().starts_with("mutation");
+        let body = json!(GqlRequest { query, variables }).to_string();
+        let resp = self.send("POST", endpoint, Some(Body::json(&body)), mutating)?;
+        gql_result(serde_json::from_str(&resp)?, query)
+    }
+
+    // `type_fields` returns names of fields of a type, or of an input type, of
+    // the admin schema. fields are added, renamed and removed between versions
+    // of dgraph, so this is how to tell which ones alpha knows about.
+    pub fn type_fields(&self, name: &str) -> Result<Vec<String>> {
+        #[derive(Deserialize, Debug)]
+        struct Field {
+            name: String,
+        }
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "camelCase")]
+        struct Type {
+            fields: Option<Vec<Field>>,
+            input_fields: Option<Vec<Field>>,
+        }
+        #[derive(Deserialize, Debug)]
+        struct Data {
+            #[serde(rename = "__type")]
+            ty: Option<Type>,
+        }
+        let resp = self.query::<_, Data>(
+            "admin",
+            r#"query type($name: String!) {
+                __type(name: $name) {
+                    fields { name }
+                    inputFields { name }
+                }
+            }"#,
+            json!({ "name": name }),
+        )?;
+        let ty = resp
+            .and_then(|data| data.ty)
+            .ok_or_else(|| anyhow!("type {} not found in admin schema", name))?;
+        Ok(ty
+            .fields
+            .into_iter()
+            .chain(ty.input_fields)
+            .flatten()
+            .map(|f| f.name)
+            .collect())
+    }
+
+    // `dql` runs a dql query against `/query`.
+    // see: https://dgraph.io/docs/clients/raw-http/#running-a-query
+    pub fn dql<Data: DeserializeOwned>(&self, query: &str) -> Result<Option<Data>> {
+        let body = Body {
+            content_type: Some("application/dql"),
+            data: query,
+        };
+        let resp = self.send("POST", "query", Some(body), false)?;
+        gql_result(serde_json::from_str(&resp)?, query)
+    }
+
+    pub fn alter(&self, payload: &str) -> Result<()> {
+        let resp = self.post_mutating("alter", payload)?;
+        // https://dgraph.io/docs/clients/raw-http/#alter-the-database says to
+        // expect `{"code":"Success","message":"Done"}`, but in fact the
+        // response is a little bit different
+        if resp != r#"{"data":{"code":"Success","message":"Done"}}"# {
+            Err(anyhow!("unexpected response: {:?}", &resp))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// `redact_body` replaces passwords in json bodies, like graphql variables of
+// `addUser` or `resetPassword`, with `<redacted>`. anything else is printed
+// as is.
+fn redact_body(body: Body) -> String {
+    if body.content_type != Some("application/json") {
+        return body.data.to_string();
+    }
+    let mut value = match serde_json::from_str::<JsonValue>(body.data) {
+        Ok(value) => value,
+        Err(_) => return body.data.to_string(),
+    };
+    if redact_passwords(&mut value) {
+        value.to_string()
+    } else {
+        body.data.to_string()
+    }
+}
+
+// `redact_passwords` returns whether there was anything to redact.
+fn redact_passwords(value: &mut JsonValue) -> bool {
+    let mut redacted = false;
+    match value {
+        JsonValue::Object(fields) => {
+            for (key, value) in fields.iter_mut() {
+                if key == "password" && !value.is_null() {
+                    *value = json!("<redacted>");
+                    redacted = true;
+                } else {
+                    redacted |= redact_passwords(value);
+                }
+            }
+        }
+        JsonValue::Array(items) => {
+            for item in items {
+                redacted |= redact_passwords(item);
+            }
+        }
+        _ => {}
+    }
+    redacted
+}
+
+// `base_url` adds "http://" to the url if it's missing a scheme, and trims
+// off the path.
+fn base_url(url: &str) -> Result<String> {
+    // if scheme is not provided (example: localhost:8080)
+    // then host may be parsed as scheme,
+    // see: https://github.com/servo/rust-url/issues/613
+    // and without a port (example: localhost) it's not a url at all
+    let mut parsed_url = match Url::parse(url) {
+        Ok(parsed_url) if parsed_url.scheme() == "http" || parsed_url.scheme() == "https" => {
+            parsed_url
+        }
+        // add scheme, if missing
+        Ok(_) | Err(url::ParseError::RelativeUrlWithoutBase) => {
+            let schemeful_url = "http://".to_string() + url;
+            Url::parse(&schemeful_url).context("your url is fucky wacky. sorry!")?
+        }
+        Err(err) => return Err(err.into()),
+    };
+    // trim off path
+    parsed_url.set_path("");
+    Ok(parsed_url.to_string())
+}
+
+fn gql_result<Data>(resp: GqlResponse<Data>, query: &str) -> Result<Option<Data>> {
+    if let Some(errors) = resp.errors {
+        Err(GqlErrors {
+            errors,
+            source: Some((String::from("query"), query.to_string())),
+        }
+        .into())
+    } else {
+        Ok(resp.data)
+    }
+}
+
+impl GqlErrors {
+    // `in_schema` points error locations to the uploaded schema instead of
+    // the query. dgraph reports schema errors with locations in the message,
+    // like `input:3: Undefined type Baz.\n (Locations: [{Line: 3, Column: 8}])`.
+    pub fn in_schema(mut self, name: &str, schema: &str) -> Self {
+        for err in &mut self.errors {
+            err.locations = None;
+            if let Some(i) = err.message.find("(Locations: [") {
+                let locations = parse_locations(&err.message[i..]);
+                if !locations.is_empty() {
+                    err.locations = Some(locations);
+                    err.message = err.message[..i].trim_end().to_string();
+                }
+            }
+        }
+        self.source = Some((name.to_string(), schema.to_string()));
+        self
+    }
+}
+
+// `parse_locations` parses go-formatted locations, like
+// `[{Line: 3, Column: 8} {Line: 5, Column: 1}]`.
+fn parse_locations(s: &str) -> Vec<Location> {
+    s.split("{Line: ")
+        .skip(1)
+        .filter_map(|part| {
+            let (line, rest) = part.split_once(", Column: ")?;
+            let column = rest.split('}').next()?;
+            Some(Location {
+                line: line.trim().parse().ok()?,
+                column: column.trim().parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+// renders errors like:
+//
+//     Undefined type Baz.
+//       --> schema.graphql:3:8
+//        |
+//      3 |   baz: Baz
+//        |        ^
+impl fmt::Display for GqlErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, err) in self.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", err.message.trim_end())?;
+            if let Some(path) = &err.path {
+                let path: Vec<String> = path
+                    .iter()
+                    .map(|p| match p {
+                        JsonValue::String(s) => s.clone(),
+                        p => p.to_string(),
+                    })
+                    .collect();
+                write!(f, "\n  at {}", path.join("."))?;
+            }
+            for location in err.locations.iter().flatten() {
+                match &self.source {
+                    Some((name, source)) => {
+                        write!(f, "\n  --> {}:{}:{}", name, location.line, location.column)?;
+                        let line = location
+                            .line
+                            .checked_sub(1)
+                            .and_then(|n| source.lines().nth(n));
+                        if let Some(line) = line {
+                            highlight(f, location, line)?;
+                        }
+                    }
+                    None => write!(f, "\n  --> {}:{}", location.line, location.column)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn highlight(f: &mut fmt::Formatter, location: &Location, line: &str) -> fmt::Result {
+    let number = location.line.to_string();
+    let gutter = " ".repeat(number.len());
+    // keep tabs, so that the caret lines up with the column
+    let indent: String = line
+        .chars()
+        .take(location.column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    write!(f, "\n  {} |", gutter)?;
+    write!(f, "\n  {} | {}", number, line)?;
+    write!(f, "\n  {} | {}^", gutter, indent)
+}
+
+impl std::error::Error for GqlErrors {}
+
+// `is_transient` checks if the request failed because dgraph could not be
+// reached, as opposed to dgraph rejecting it.
+fn is_transient(err: &anyhow::Error) -> bool {
+    if let Some(err) = err.downcast_ref::<ureq::Error>() {
+        return matches!(
+            err.kind(),
+            ureq::ErrorKind::ConnectionFailed | ureq::ErrorKind::Io
+        );
+    }
+    err.is::<io::Error>()
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorsOnly {
+    errors: Option<Vec<GqlError>>,
+}
+
+// `is_token_expired` checks if dgraph rejected the request because access
+// token has expired. both graphql and http endpoints report it as an error
+// with "Token is expired" message.
+fn is_token_expired(resp: &str) -> bool {
+    serde_json::from_str::<ErrorsOnly>(resp)
+        .ok()
+        .and_then(|r| r.errors)
+        .is_some_and(|errors| {
+            errors
+                .iter()
+                .any(|e| e.message.to_lowercase().contains("token is expired"))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_url_adds_scheme_and_trims_path() {
+        let cases = [
+            ("localhost:8080", "http://localhost:8080/"),
+            ("prod.example.com", "http://prod.example.com/"),
+            ("localhost", "http://localhost/"),
+            ("https://example.com/graphql", "https://example.com/"),
+            ("http://10.0.0.1:8080/admin", "http://10.0.0.1:8080/"),
+        ];
+        for (url, expected) in &cases {
+            assert_eq!(&base_url(url).unwrap(), expected, "{}", url);
+        }
+    }
+    #[test]
+    fn redact_nested_passwords() {
+        let data = r#"{"query":"mutation","variables":{"input":[{"name":"a","password":"secret"},{"name":"b","password":"hunter2"}]}}"#;
+        let redacted: JsonValue = serde_json::from_str(&redact_body(Body::json(data))).unwrap();
+        assert_eq!(
+            redacted["variables"]["input"],
+            json!([
+                {"name": "a", "password": "<redacted>"},
+                {"name": "b", "password": "<redacted>"},
+            ])
+        );
+        assert_eq!(redacted["query"], "mutation");
+    }
+
+    #[test]
+    fn redact_leaves_null_passwords() {
+        let mut value = json!({"input": {"name": "a", "password": null}});
+        assert!(!redact_passwords(&mut value));
+        assert_eq!(value, json!({"input": {"name": "a", "password": null}}));
+
+        // nothing to redact, so the body is printed as it was given.
+        let data = r#"{"input": {"password": null}}"#;
+        assert_eq!(redact_body(Body::json(data)), data);
+    }
+
+    #[test]
+    fn redact_keeps_non_json_bodies() {
+        let data = "password: secret";
+        let body = Body {
+            content_type: None,
+            data,
+        };
+        assert_eq!(redact_body(body), data);
+        assert_eq!(redact_body(Body::json(data)), data);
+    }
+    #[test]
+    fn for_each_request_goes_past_requests_not_sent() {
+        let dry_run = Dgraph::new(
+            String::from("localhost:8080"),
+            None,
+            None,
+            Options {
+                dry_run: true,
+                ..Options::default()
+            },
+        )
+        .unwrap();
+        let mut seen = Vec::new();
+        let err = dry_run
+            .for_each_request(1..=3, |i| {
+                seen.push(i);
+                dry_run.post_mutating("alter", "drop_all")
+            })
+            .unwrap_err();
+        assert!(err.is::<DryRun>());
+        assert_eq!(seen, [1, 2, 3]);
+        assert_eq!(dry_run.dry_run_report().requests.len(), 3);
+
+        // anything else stops it
+        let mut seen = Vec::new();
+        let err = dry_run
+            .for_each_request(1..=3, |i| {
+                seen.push(i);
+                match i {
+                    2 => Err(anyhow!("failed")),
+                    _ => Ok(i),
+                }
+            })
+            .unwrap_err();
+        assert_eq!(err.to_string(), "failed");
+        assert_eq!(seen, [1, 2]);
+    }
+
+    #[test]
+    fn dry_run_reports_requests_of_every_node() {
+        let options = Options {
+            dry_run: true,
+            ..Options::default()
+        };
+        let credentials = Credentials {
+            user: String::from("groot"),
+            password: String::from("password"),
+            namespace: None,
+        };
+        let dgraph = Dgraph::new(
+            String::from("alpha1:8080"),
+            Some(String::from("X-Dgraph-AuthToken: secret")),
+            Some(credentials),
+            options,
+        )
+        .unwrap();
+        let err = dgraph.post_mutating("alter", "drop_all").unwrap_err();
+        assert!(err.is::<DryRun>());
+        let alpha2 = dgraph.with_url("alpha2:8080").unwrap();
+        let err = alpha2
+            .query::<_, JsonValue>(
+                "admin",
+                "mutation add($input: [AddUserInput!]!) { addUser(input: $input) { numUids } }",
+                json!({"input": [{"name": "alice", "password": "secret"}]}),
+            )
+            .unwrap_err();
+        assert!(err.is::<DryRun>());
+
+        let report = serde_json::to_value(dgraph.dry_run_report()).unwrap();
+        let requests = report["requests"].as_array().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0]["url"], "http://alpha1:8080/alter");
+        assert_eq!(requests[0]["body"], "drop_all");
+        assert_eq!(
+            requests[1]["headers"],
+            json!({
+                "Content-Type": "application/json",
+                "X-Dgraph-AccessToken": "<redacted>",
+                "X-Dgraph-AuthToken": "<redacted>",
+            })
+        );
+        assert!(!requests[1]["body"].as_str().unwrap().contains("secret"));
+    }
+    fn schema_error(message: &str) -> GqlErrors {
+        GqlErrors {
+            errors: vec![GqlError {
+                message: message.to_string(),
+                locations: Some(vec![Location { line: 1, column: 1 }]),
+                path: None,
+                extensions: None,
+            }],
+            source: None,
+        }
+    }
+
+    fn positions(locations: &[Location]) -> Vec<(usize, usize)> {
+        locations.iter().map(|l| (l.line, l.column)).collect()
+    }
+
+    #[test]
+    fn parses_go_formatted_locations() {
+        assert_eq!(
+            positions(&parse_locations("(Locations: [{Line: 3, Column: 8}])")),
+            [(3, 8)]
+        );
+        assert_eq!(
+            positions(&parse_locations(
+                "(Locations: [{Line: 3, Column: 8} {Line: 12, Column: 1}])"
+            )),
+            [(3, 8), (12, 1)]
+        );
+        assert!(parse_locations("(Locations: [])").is_empty());
+        assert!(parse_locations("{Line: x, Column: 1}").is_empty());
+    }
+
+    #[test]
+    fn in_schema_moves_locations_out_of_message() {
+        let errors = schema_error(
+            "input:3: Undefined type Baz.\n (Locations: [{Line: 3, Column: 8} {Line: 4, Column: 2}])",
+        )
+        .in_schema("schema.graphql", "");
+        let err = &errors.errors[0];
+        assert_eq!(err.message, "input:3: Undefined type Baz.");
+        assert_eq!(positions(err.locations.as_ref().unwrap()), [(3, 8), (4, 2)]);
+
+        // locations of the query don't point to anything in the schema
+        let errors = schema_error("input: schema is empty").in_schema("schema.graphql", "");
+        let err = &errors.errors[0];
+        assert_eq!(err.message, "input: schema is empty");
+        assert!(err.locations.is_none());
+    }
+
+    #[test]
+    fn highlights_locations_in_schema() {
+        let schema = "type A {\n  baz: Baz\n  qux: [Qux]\n}\n";
+        let errors = schema_error(
+            "Undefined type Baz.\n (Locations: [{Line: 2, Column: 8} {Line: 3, Column: 9}])",
+        )
+        .in_schema("schema.graphql", schema);
+        assert_eq!(
+            errors.to_string(),
+            "Undefined type Baz.
+  --> schema.graphql:2:8
+    |
+  2 |   baz: Baz
+    |        ^
+  --> schema.graphql:3:9
+    |
+  3 |   qux: [Qux]
+    |         ^"
+        );
+    }
+
+    #[test]
+    fn highlight_keeps_tabs() {
+        let schema = "type A {\n\tbaz: Baz\n}\n";
+        let errors = schema_error("Undefined type Baz.\n (Locations: [{Line: 2, Column: 7}])")
+            .in_schema("schema.graphql", schema);
+        assert_eq!(
+            errors.to_string(),
+            "Undefined type Baz.\n  --> schema.graphql:2:7\n    |\n  2 | \tbaz: Baz\n    | \t     ^"
+        );
+    }
+}
@@ -0,0 +1,153 @@
+// dql schema, as opposed to graphql schema, describes predicates and types
+// directly.
+// see: https://dgraph.io/docs/query-language/schema/
+
+use crate::{
+    dgraph::Dgraph,
+    output::{Done, Report, DONE},
+};
+use anyhow::Result;
+use argh::FromArgs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "get-dql-schema",
+    description = "get the current dql schema"
+)]
+pub struct GetDqlSchema {
+    #[argh(
+        switch,
+        description = "include internal predicates and types (dgraph.*)"
+    )]
+    all: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct Predicate {
+    predicate: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    list: bool,
+    #[serde(default)]
+    index: bool,
+    #[serde(default)]
+    tokenizer: Vec<String>,
+    #[serde(default)]
+    reverse: bool,
+    #[serde(default)]
+    count: bool,
+    #[serde(default)]
+    upsert: bool,
+    #[serde(default)]
+    lang: bool,
+}
+
+impl Predicate {
+    // `to_dql` formats the predicate the way it's written in dql schema,
+    // for example `name: string @index(exact, term) @lang .`
+    fn to_dql(&self) -> String {
+        let mut s = format!("{}: ", &self.predicate);
+        if self.list {
+            s += &format!("[{}]", &self.kind);
+        } else {
+            s += &self.kind;
+        }
+        if self.index {
+            s += &format!(" @index({})", self.tokenizer.join(", "));
+        }
+        for (enabled, directive) in &[
+            (self.reverse, "@reverse"),
+            (self.count, "@count"),
+            (self.upsert, "@upsert"),
+            (self.lang, "@lang"),
+        ] {
+            if *enabled {
+                s += " ";
+                s += directive;
+            }
+        }
+        s + " ."
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct TypeField {
+    name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct Type {
+    name: String,
+    #[serde(default)]
+    fields: Vec<TypeField>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SchemaData {
+    #[serde(default)]
+    schema: Vec<Predicate>,
+    #[serde(default)]
+    types: Vec<Type>,
+}
+
+impl Report for SchemaData {
+    fn print_text(&self) {
+        if self.schema.is_empty() && self.types.is_empty() {
+            println!("no schema");
+            return;
+        }
+        for p in &self.schema {
+            println!("{}", p.to_dql());
+        }
+        for t in &self.types {
+            println!();
+            println!("type {} {{", &t.name);
+            for f in &t.fields {
+                println!("  {}", &f.name);
+            }
+            println!("}}");
+        }
+    }
+}
+
+fn is_internal(name: &str) -> bool {
+    name.starts_with("dgraph.")
+}
+
+impl GetDqlSchema {
+    pub fn exec(self, dgraph: &Dgraph) -> Result<SchemaData> {
+        let mut data = dgraph
+            .dql::<SchemaData>("schema {}")?
+            .unwrap_or(SchemaData {
+                schema: Vec::new(),
+                types: Vec::new(),
+            });
+        if !self.all {
+            data.schema.retain(|p| !is_internal(&p.predicate));
+            data.types.retain(|t| !is_internal(&t.name));
+        }
+        Ok(data)
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "update-dql-schema",
+    description = "add or modify dql schema"
+)]
+pub struct UpdateDqlSchema {
+    #[argh(positional)]
+    file: String,
+}
+impl UpdateDqlSchema {
+    pub fn exec(self, dgraph: &Dgraph) -> Result<Done> {
+        let schema = fs::read_to_string(self.file)?;
+        dgraph.alter(&schema)?;
+        Ok(DONE)
+    }
+}
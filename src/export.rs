@@ -0,0 +1,119 @@
+use crate::{
+    dgraph::{Dgraph, Response},
+    output::Report,
+};
+use anyhow::{anyhow, Result};
+use argh::FromArgs;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "export",
+    description = "export data and schema"
+)]
+pub struct Export {
+    #[argh(
+        option,
+        description = "export format, rdf or json",
+        default = "String::from(\"rdf\")"
+    )]
+    format: String,
+
+    #[argh(
+        option,
+        description = "where to export to, a path on alpha or s3/minio url (default: alpha's export dir)"
+    )]
+    destination: Option<String>,
+
+    #[argh(
+        option,
+        description = "namespace to export (default: all namespaces)"
+    )]
+    namespace: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ExportPayload {
+    response: Option<Response>,
+    exported_files: Option<Vec<String>>,
+    task_id: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Exported {
+    message: Option<String>,
+    // newer versions of dgraph export in the background and report a task id
+    // instead of files
+    task_id: Option<String>,
+    exported_files: Option<Vec<String>>,
+}
+
+impl Report for Exported {
+    fn print_text(&self) {
+        if let Some(message) = &self.message {
+            println!("{}", message);
+        }
+        if let Some(task_id) = &self.task_id {
+            println!("task id: {}", task_id);
+        }
+        if let Some(files) = &self.exported_files {
+            println!("exported files:");
+            for f in files {
+                println!("  {}", f);
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ExportData {
+    export: ExportPayload,
+}
+
+impl Export {
+    pub fn exec(self, dgraph: &Dgraph) -> Result<Exported> {
+        if !(self.format == "rdf" || self.format == "json") {
+            return Err(anyhow!(
+                "unknown format {:?}, expected rdf or json",
+                &self.format
+            ));
+        }
+        // asking for a field that this version of dgraph doesn't have fails
+        // the whole mutation
+        let known = dgraph.type_fields("ExportPayload")?;
+        let fields: Vec<&str> = ["exportedFiles", "taskId"]
+            .iter()
+            .copied()
+            .filter(|field| known.iter().any(|k| k == field))
+            .collect();
+        let resp = dgraph.query::<_, ExportData>(
+            "admin",
+            &format!(
+                r#"mutation export($input: ExportInput!) {{
+                    export(input: $input) {{
+                        response {{ message }}
+                        {}
+                    }}
+                }}"#,
+                fields.join(" ")
+            ),
+            json!({
+                "input": {
+                    "format": &self.format,
+                    "destination": &self.destination,
+                    "namespace": &self.namespace,
+                }
+            }),
+        )?;
+        let export = resp.ok_or_else(|| anyhow!("empty response"))?.export;
+        Ok(Exported {
+            message: export.response.map(|r| r.message),
+            task_id: export.task_id,
+            exported_files: export.exported_files,
+        })
+    }
+}
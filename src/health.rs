@@ -0,0 +1,255 @@
+// health of alphas and zeros, as reported by `/health`.
+// see: https://dgraph.io/docs/deploy/dgraph-alpha/#querying-health
+
+use crate::{
+    dgraph::Dgraph,
+    output::{self, Format, Report, EXIT_CHECK_FAILED},
+    parse_duration,
+    table::{format_table, print_table},
+    watch::{self, highlight, Change, Snapshot},
+};
+use anyhow::{anyhow, Result};
+use argh::FromArgs;
+use humantime::format_duration;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "get-health", description = "get status of nodes")]
+pub struct GetHealth {
+    #[argh(
+        switch,
+        description = "get status of all nodes in the cluster, not only of the one behind --url"
+    )]
+    all: bool,
+
+    #[argh(
+        switch,
+        description = "exit with code 2 if any of the nodes is not healthy"
+    )]
+    fail_unhealthy: bool,
+
+    #[argh(
+        switch,
+        description = "poll until interrupted, highlighting nodes that changed"
+    )]
+    watch: bool,
+
+    #[argh(
+        option,
+        from_str_fn(parse_duration),
+        default = "Duration::from_secs(2)",
+        description = "how often to poll with --watch (default: 2s)"
+    )]
+    interval: Duration,
+}
+
+// field names are the same as in `/health` response.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Node {
+    // alpha or zero
+    pub instance: String,
+    pub address: String,
+    pub status: String,
+    pub group: Option<String>,
+    pub version: Option<String>,
+    // seconds
+    pub uptime: u64,
+    // unix time of the last heartbeat
+    #[serde(rename = "lastEcho")]
+    pub last_echo: Option<i64>,
+    // operations in progress, like `opIndexing` or `opRestore`
+    #[serde(default)]
+    pub ongoing: Vec<String>,
+    // predicates being indexed
+    #[serde(default)]
+    pub indexing: Vec<String>,
+    #[serde(default)]
+    pub ee_features: Vec<String>,
+    pub max_assigned: Option<u64>,
+}
+
+impl Node {
+    pub fn is_healthy(&self) -> bool {
+        self.status == "healthy"
+    }
+}
+
+#[derive(Serialize)]
+pub struct Health {
+    // whether all of the nodes are healthy
+    healthy: bool,
+    nodes: Vec<Node>,
+    #[serde(skip)]
+    fail_unhealthy: bool,
+}
+
+const HEADERS: &[&str] = &[
+    "INSTANCE",
+    "ADDRESS",
+    "GROUP",
+    "STATUS",
+    "VERSION",
+    "UPTIME",
+    "LAST ECHO",
+    "MAX ASSIGNED",
+    "ONGOING",
+    "INDEXING",
+];
+
+impl Health {
+    fn rows(&self) -> Vec<Vec<String>> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        self.nodes
+            .iter()
+            .map(|n| {
+                vec![
+                    n.instance.clone(),
+                    n.address.clone(),
+                    n.group.clone().unwrap_or_default(),
+                    n.status.clone(),
+                    n.version.clone().unwrap_or_default(),
+                    format_duration(Duration::from_secs(n.uptime)).to_string(),
+                    match n.last_echo {
+                        Some(t) if t > 0 => format!(
+                            "{} ago",
+                            format_duration(Duration::from_secs((now - t).max(0) as u64))
+                        ),
+                        _ => String::from("-"),
+                    },
+                    n.max_assigned.map(|m| m.to_string()).unwrap_or_default(),
+                    n.ongoing.join(","),
+                    n.indexing.join(","),
+                ]
+            })
+            .collect()
+    }
+
+    fn print_summary(&self) {
+        let healthy = self.nodes.iter().filter(|n| n.is_healthy()).count();
+        println!();
+        println!("{}/{} nodes are healthy", healthy, self.nodes.len());
+    }
+}
+
+impl Report for Health {
+    fn print_text(&self) {
+        print_table(HEADERS, &self.rows());
+        self.print_summary();
+    }
+
+    fn exit_code(&self) -> i32 {
+        if self.fail_unhealthy && !self.healthy {
+            EXIT_CHECK_FAILED
+        } else {
+            0
+        }
+    }
+}
+
+impl Snapshot for Health {
+    fn changes(&self, previous: &Self) -> Vec<Change> {
+        let before: HashMap<&str, &Node> = previous
+            .nodes
+            .iter()
+            .map(|n| (n.address.as_str(), n))
+            .collect();
+        let mut changes = Vec::new();
+        let mut change = |node: &Node, description: String| {
+            changes.push(Change {
+                key: node.address.clone(),
+                description,
+            })
+        };
+        for node in &self.nodes {
+            let prev = match before.get(node.address.as_str()) {
+                Some(prev) => prev,
+                None => {
+                    change(node, format!("joined, {}", &node.status));
+                    continue;
+                }
+            };
+            if node.status != prev.status {
+                change(node, format!("{} -> {}", &prev.status, &node.status));
+            }
+            // restarted nodes start counting from zero, restarting over and
+            // over again means it's crashing
+            if node.uptime < prev.uptime {
+                change(
+                    node,
+                    format!(
+                        "restarted, uptime was {}",
+                        format_duration(Duration::from_secs(prev.uptime))
+                    ),
+                );
+            }
+            for op in node.ongoing.iter().filter(|op| !prev.ongoing.contains(op)) {
+                change(node, format!("started {}", op));
+            }
+            for op in prev.ongoing.iter().filter(|op| !node.ongoing.contains(op)) {
+                change(node, format!("finished {}", op));
+            }
+        }
+        let now: HashSet<&str> = self.nodes.iter().map(|n| n.address.as_str()).collect();
+        for node in previous
+            .nodes
+            .iter()
+            .filter(|n| !now.contains(n.address.as_str()))
+        {
+            change(node, String::from("left"));
+        }
+        changes
+    }
+
+    fn print_highlighted(&self, highlighted: &HashSet<String>) {
+        let lines = format_table(HEADERS, &self.rows());
+        println!("{}", &lines[0]);
+        for (node, line) in self.nodes.iter().zip(&lines[1..]) {
+            if highlighted.contains(&node.address) {
+                println!("{}", highlight(line));
+            } else {
+                println!("{}", line);
+            }
+        }
+        self.print_summary();
+    }
+}
+
+impl GetHealth {
+    pub fn run(self, dgraph: &Dgraph, format: Format) -> Result<i32> {
+        if !self.watch {
+            return output::print(format, &self.exec(dgraph)?);
+        }
+        if format != Format::Text {
+            return Err(anyhow!("--watch works only with text output"));
+        }
+        let title = if self.all {
+            "get-health --all"
+        } else {
+            "get-health"
+        };
+        watch::watch(title, self.interval, || self.exec(dgraph))
+    }
+
+    fn exec(&self, dgraph: &Dgraph) -> Result<Health> {
+        let nodes = get_nodes(dgraph, self.all)?;
+        Ok(Health {
+            // a cluster with no nodes is not much of a cluster
+            healthy: !nodes.is_empty() && nodes.iter().all(Node::is_healthy),
+            nodes,
+            fail_unhealthy: self.fail_unhealthy,
+        })
+    }
+}
+
+// `get_nodes` returns the node behind the url, or, with `all`, every node of
+// the cluster.
+pub fn get_nodes(dgraph: &Dgraph, all: bool) -> Result<Vec<Node>> {
+    dgraph.get(if all { "health?all" } else { "health" })
+}
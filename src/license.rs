@@ -0,0 +1,162 @@
+// enterprise license, which enables acl, backups, multi-tenancy and the rest
+// of enterprise features.
+// see: https://dgraph.io/docs/enterprise-features/license/
+
+use crate::{
+    dgraph::Dgraph,
+    health::get_nodes,
+    output::{self, Format, Report},
+    state::{self, get_state},
+    zero::{Zero, DEFAULT_URL},
+};
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use humantime::{format_duration, format_rfc3339_seconds};
+use serde::Serialize;
+use std::{
+    fs,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "license",
+    description = "show or apply enterprise license"
+)]
+pub struct License {
+    #[argh(subcommand)]
+    subcommand: LicenseSubCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum LicenseSubCommand {
+    Show(LicenseShow),
+    Apply(LicenseApply),
+}
+
+impl License {
+    pub fn exec(self, dgraph: &Dgraph, format: Format) -> Result<i32> {
+        match self.subcommand {
+            LicenseSubCommand::Show(x) => output::print(format, &x.exec(dgraph)?),
+            LicenseSubCommand::Apply(x) => output::print(format, &x.exec(dgraph)?),
+        }
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "show",
+    description = "show the license and enterprise features enabled on the alpha"
+)]
+struct LicenseShow {
+    #[argh(
+        option,
+        description = "zero's http url (like localhost:6080) to get the license from, instead of alpha's admin endpoint"
+    )]
+    zero_url: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LicenseInfo {
+    // `None` if dgraph didn't report any
+    license: Option<state::License>,
+    // as reported by `/health` of the alpha behind the url
+    ee_features: Vec<String>,
+}
+
+impl Report for LicenseInfo {
+    fn print_text(&self) {
+        match &self.license {
+            Some(license) => {
+                let max_nodes = match license.max_nodes {
+                    u64::MAX => String::from("unlimited"),
+                    n => n.to_string(),
+                };
+                println!("enabled      {}", license.enabled);
+                println!("user         {}", &license.user);
+                println!("max nodes    {}", max_nodes);
+                println!("expires      {}", format_expiry(license.expiry_ts));
+            }
+            None => println!("no license"),
+        }
+        let features = if self.ee_features.is_empty() {
+            String::from("none")
+        } else {
+            self.ee_features.join(", ")
+        };
+        println!("ee features  {}", features);
+    }
+}
+
+// `format_expiry` formats the expiry time along with how long it's from now,
+// like `2021-06-01T00:00:00Z (in 5days 3h)`.
+fn format_expiry(expiry_ts: u64) -> String {
+    if expiry_ts == 0 {
+        return String::from("-");
+    }
+    let expiry = UNIX_EPOCH + Duration::from_secs(expiry_ts);
+    // whole seconds, the rest is noise
+    let round = |d: Duration| format_duration(Duration::from_secs(d.as_secs()));
+    let relative = match expiry.duration_since(SystemTime::now()) {
+        Ok(left) => format!("in {}", round(left)),
+        Err(err) => format!("expired {} ago", round(err.duration())),
+    };
+    format!("{} ({})", format_rfc3339_seconds(expiry), relative)
+}
+
+impl LicenseShow {
+    fn exec(self, dgraph: &Dgraph) -> Result<LicenseInfo> {
+        let state = get_state(dgraph, self.zero_url.as_deref())?;
+        let ee_features = get_nodes(dgraph, false)?
+            .into_iter()
+            .flat_map(|n| n.ee_features)
+            .collect();
+        Ok(LicenseInfo {
+            license: state.license,
+            ee_features,
+        })
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "apply",
+    description = "apply an enterprise license"
+)]
+struct LicenseApply {
+    #[argh(positional, description = "license file, as issued by dgraph")]
+    file: String,
+
+    #[argh(
+        option,
+        default = "String::from(DEFAULT_URL)",
+        description = "zero's http url (default: localhost:6080)"
+    )]
+    zero_url: String,
+}
+
+#[derive(Serialize)]
+struct LicenseApplied {
+    message: String,
+}
+
+impl Report for LicenseApplied {
+    fn print_text(&self) {
+        println!("{}", &self.message);
+    }
+}
+
+impl LicenseApply {
+    fn exec(self, dgraph: &Dgraph) -> Result<LicenseApplied> {
+        let license = fs::read_to_string(&self.file)
+            .with_context(|| format!("could not read {}", &self.file))?;
+        Ok(LicenseApplied {
+            message: Zero::new(dgraph, &self.zero_url)?.apply_license(&license)?,
+        })
+    }
+}
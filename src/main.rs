@@ -1,109 +1,38 @@
 use anyhow::{anyhow, Context, Result};
 use argh::FromArgs;
-use humantime::format_duration;
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use dgraph::{Credentials, Dgraph, DryRun, GqlErrors, Options};
+use output::{parse_format, Done, Format, Report, DONE, EXIT_CHECK_FAILED};
+use serde::Serialize;
 use serde_json::{json, Value as JsonValue};
-use std::{fs, time::Duration};
-use url::Url;
-
-struct Dgraph {
-    base_url: String,
-    auth_header: Option<String>,
-}
-
-#[derive(Serialize, Debug)]
-struct GqlRequest<'q, Variables> {
-    query: &'q str,
-    variables: Variables,
-}
-
-#[derive(Deserialize, Debug)]
-struct GqlError {
-    message: String,
-}
-
-// spec: https://spec.graphql.org/June2018/#sec-Response-Format
-#[derive(Deserialize, Debug)]
-struct GqlResponse<Data> {
-    data: Option<Data>,
-    errors: Option<Vec<GqlError>>,
-}
-
-impl Dgraph {
-    // `new` constructs a new client for dgraph.
-    // if the given url is missing a scheme (such as "http://" or "https://")
-    // then "http://" will be used.
-    // url must not end with `/graphql`, if it is - it will be trimmed off.
-    fn new(url: String, auth_header: Option<String>) -> Result<Self> {
-        // if scheme is not provided (example: localhost:8080)
-        // then host may be parsed as scheme,
-        // see: https://github.com/servo/rust-url/issues/613
-        let mut parsed_url = Url::parse(&url)?;
-        // add scheme, if missing
-        let scheme = parsed_url.scheme();
-        if !(scheme == "http" || scheme == "https") {
-            let schemeful_url = "http://".to_string() + &url;
-            parsed_url = Url::parse(&schemeful_url).context("your url is fucky wacky. sorry!")?;
-        }
-        // trim off path
-        parsed_url.set_path("");
-
-        Ok(Self {
-            base_url: parsed_url.to_string(),
-            auth_header,
-        })
-    }
-
-    fn add_auth_header(&self, req: ureq::Request) -> ureq::Request {
-        if let Some(ah) = &self.auth_header {
-            if let Some((key, value)) = ah.split_once(':') {
-                return req.set(key, value);
-            }
-        }
-        req
-    }
-
-    fn post(&self, endpoint: &str) -> ureq::Request {
-        let req = ureq::post(&(self.base_url.clone() + endpoint));
-        self.add_auth_header(req)
-    }
-
-    fn get(&self, endpoint: &str) -> ureq::Request {
-        let req = ureq::get(&(self.base_url.clone() + endpoint));
-        self.add_auth_header(req)
-    }
+use std::{
+    env, fs,
+    io::{self, Write},
+    process,
+    time::Duration,
+};
+use tls::TlsOptions;
 
-    fn query<Variables: Serialize, Data: DeserializeOwned>(
-        &self,
-        endpoint: &str,
-        query: &str,
-        variables: Variables,
-    ) -> Result<Option<Data>> {
-        let resp: GqlResponse<Data> = self
-            .post(endpoint)
-            .send_json(json!(GqlRequest { query, variables }))?
-            .into_json()?;
-        if let Some(errors) = resp.errors {
-            // gql errors can be prettier, but do i expect to see them often
-            // to care enough? no.
-            Err(anyhow!("{:#?}", &errors))
-        } else {
-            Ok(resp.data)
-        }
-    }
-
-    fn alter(&self, payload: &str) -> Result<()> {
-        let resp = self.post("alter").send_string(payload)?.into_string()?;
-        // https://dgraph.io/docs/clients/raw-http/#alter-the-database says to
-        // expect `{"code":"Success","message":"Done"}`, but in fact the
-        // response is a little bit different
-        if resp != r#"{"data":{"code":"Success","message":"Done"}}"# {
-            Err(anyhow!("unexpected response: {:?}", &resp))
-        } else {
-            Ok(())
-        }
-    }
-}
+mod acl;
+mod backup;
+mod cluster;
+mod config;
+mod dgraph;
+mod dql;
+mod export;
+mod health;
+mod license;
+mod maintenance;
+mod namespace;
+mod output;
+mod runtime_config;
+mod schema;
+mod secret;
+mod state;
+mod table;
+mod tablet;
+mod tls;
+mod watch;
+mod zero;
 
 #[derive(FromArgs)]
 #[argh(
@@ -114,21 +43,102 @@ impl Dgraph {
 struct UpdateSchema {
     #[argh(positional)]
     file: String,
+
+    #[argh(
+        switch,
+        description = "apply the schema even if it has breaking changes"
+    )]
+    allow_breaking: bool,
+}
+#[derive(Serialize)]
+struct SchemaChange {
+    change: String,
+    // why the change is breaking, if it is
+    breaking: Option<&'static str>,
+}
+
+impl From<schema::Change> for SchemaChange {
+    fn from(change: schema::Change) -> Self {
+        Self {
+            breaking: change.breaking_reason(),
+            change: change.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SchemaUpdate {
+    // changes compared to the previous schema, empty with --allow-breaking
+    changes: Vec<SchemaChange>,
+}
+
+impl Report for SchemaUpdate {
+    fn print_text(&self) {
+        for c in &self.changes {
+            match c.breaking {
+                Some(reason) => println!("breaking: {} ({})", &c.change, reason),
+                None => println!("safe:     {}", &c.change),
+            }
+        }
+        println!("success");
+    }
 }
+
 impl UpdateSchema {
-    fn exec(self, dgraph: &Dgraph) -> Result<()> {
-        let schema = fs::read_to_string(self.file)?;
-        let _ = dgraph.query::<JsonValue, JsonValue>(
-            "admin",
-            r#"mutation updateGQLSchema($schema: String!) {
+    fn exec(self, dgraph: &Dgraph) -> Result<SchemaUpdate> {
+        let schema = fs::read_to_string(&self.file)?;
+        let changes = if self.allow_breaking {
+            Vec::new()
+        } else {
+            self.check_breaking_changes(dgraph, &schema)?
+        };
+        let _ = dgraph
+            .query::<JsonValue, JsonValue>(
+                "admin",
+                r#"mutation updateGQLSchema($schema: String!) {
                 updateGQLSchema(input: { set: { schema: $schema } }) {
                     gqlSchema { id }
                 }
             }"#,
-            json!({ "schema": &schema }),
-        )?;
-        println!("success");
-        Ok(())
+                json!({ "schema": &schema }),
+            )
+            .map_err(|err| match err.downcast::<GqlErrors>() {
+                Ok(errors) => errors.in_schema(&self.file, &schema).into(),
+                Err(err) => err,
+            })?;
+        Ok(SchemaUpdate { changes })
+    }
+
+    // `check_breaking_changes` compares the schema with the current one and
+    // fails if there are changes that may break existing data or indexes.
+    fn check_breaking_changes(&self, dgraph: &Dgraph, schema: &str) -> Result<Vec<SchemaChange>> {
+        let new = schema::Schema::parse(schema).with_context(|| {
+            format!(
+                "could not parse {} (use --allow-breaking to skip the check)",
+                &self.file
+            )
+        })?;
+        let live = schema::Schema::parse(&get_gql_schema(dgraph)?)
+            .context("could not parse the current schema")?;
+        let changes: Vec<SchemaChange> = schema::diff(&live, &new)
+            .into_iter()
+            .map(SchemaChange::from)
+            .collect();
+        let breaking: Vec<String> = changes
+            .iter()
+            .filter_map(|c| {
+                c.breaking
+                    .map(|reason| format!("  {} ({})", &c.change, reason))
+            })
+            .collect();
+        if !breaking.is_empty() {
+            return Err(anyhow!(
+                "refusing to apply {} breaking change(s), use --allow-breaking to apply anyway:\n{}",
+                breaking.len(),
+                breaking.join("\n")
+            ));
+        }
+        Ok(changes)
     }
 }
 
@@ -139,29 +149,103 @@ impl UpdateSchema {
     description = "get the current schema"
 )]
 struct GetSchema {}
+
+#[derive(Serialize)]
+struct GqlSchema {
+    // empty if there's no schema
+    schema: String,
+}
+
+impl Report for GqlSchema {
+    fn print_text(&self) {
+        if self.schema.is_empty() {
+            println!("no schema");
+        } else {
+            println!("{}", &self.schema);
+        }
+    }
+}
+
 impl GetSchema {
-    fn exec(self, dgraph: &Dgraph) -> Result<()> {
-        let resp = dgraph.query::<(), JsonValue>(
-            "admin",
-            r#"query getGQLSchema {
-                getGQLSchema { schema }
-            }"#,
-            (),
-        )?;
-        if let Some(data) = resp {
-            // NOTE: schema is null on a new database, but if drop-all
-            // was called - schema is ""(empty string).
-            let schema = data["getGQLSchema"]["schema"]
-                .as_str()
-                .unwrap_or_default()
-                .trim();
-            if schema.is_empty() {
-                println!("no schema");
-            } else {
-                println!("{}", schema);
-            }
+    fn exec(self, dgraph: &Dgraph) -> Result<GqlSchema> {
+        Ok(GqlSchema {
+            schema: get_gql_schema(dgraph)?,
+        })
+    }
+}
+
+// `get_gql_schema` returns the current graphql schema, or an empty string
+// if there's none.
+fn get_gql_schema(dgraph: &Dgraph) -> Result<String> {
+    let resp = dgraph.query::<(), JsonValue>(
+        "admin",
+        r#"query getGQLSchema {
+            getGQLSchema { schema }
+        }"#,
+        (),
+    )?;
+    // NOTE: schema is null on a new database, but if drop-all
+    // was called - schema is ""(empty string).
+    Ok(resp
+        .as_ref()
+        .and_then(|data| data["getGQLSchema"]["schema"].as_str())
+        .unwrap_or_default()
+        .trim()
+        .to_string())
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "diff-schema",
+    description = "compare a schema file with the current schema"
+)]
+struct DiffSchema {
+    #[argh(positional)]
+    file: String,
+}
+#[derive(Serialize)]
+struct SchemaDiff {
+    #[serde(skip)]
+    file: String,
+    changes: Vec<SchemaChange>,
+}
+
+impl Report for SchemaDiff {
+    fn print_text(&self) {
+        if self.changes.is_empty() {
+            println!("no changes");
+            return;
+        }
+        for c in &self.changes {
+            println!("{}", &c.change);
         }
-        Ok(())
+        eprintln!("{} differs from the current schema", &self.file);
+    }
+
+    fn exit_code(&self) -> i32 {
+        if self.changes.is_empty() {
+            0
+        } else {
+            EXIT_CHECK_FAILED
+        }
+    }
+}
+
+impl DiffSchema {
+    fn exec(self, dgraph: &Dgraph) -> Result<SchemaDiff> {
+        let local = schema::Schema::parse(&fs::read_to_string(&self.file)?)
+            .with_context(|| format!("could not parse {}", &self.file))?;
+        let live = schema::Schema::parse(&get_gql_schema(dgraph)?)
+            .context("could not parse the current schema")?;
+        let changes = schema::diff(&live, &local)
+            .into_iter()
+            .map(SchemaChange::from)
+            .collect();
+        Ok(SchemaDiff {
+            file: self.file,
+            changes,
+        })
     }
 }
 
@@ -171,12 +255,15 @@ impl GetSchema {
     name = "drop-all",
     description = "drop all data and schema"
 )]
-struct DropAll {}
+struct DropAll {
+    #[argh(switch, description = "do not ask for confirmation")]
+    yes: bool,
+}
 impl DropAll {
-    fn exec(self, dgraph: &Dgraph) -> Result<()> {
+    fn exec(self, dgraph: &Dgraph) -> Result<Done> {
+        confirm(dgraph, "drop all data and schema", self.yes)?;
         dgraph.alter(r#"{"drop_all": true}"#)?;
-        println!("success");
-        Ok(())
+        Ok(DONE)
     }
 }
 
@@ -186,61 +273,175 @@ impl DropAll {
     name = "drop-data",
     description = "drop all data only (keep schema)"
 )]
-struct DropData {}
+struct DropData {
+    #[argh(switch, description = "do not ask for confirmation")]
+    yes: bool,
+}
 impl DropData {
-    fn exec(self, dgraph: &Dgraph) -> Result<()> {
+    fn exec(self, dgraph: &Dgraph) -> Result<Done> {
+        confirm(dgraph, "drop all data", self.yes)?;
         dgraph.alter(r#"{"drop_op": "DATA"}"#)?;
-        println!("success");
-        Ok(())
+        Ok(DONE)
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "drop-attr",
+    description = "drop predicates with all of their data"
+)]
+struct DropAttr {
+    #[argh(positional)]
+    predicates: Vec<String>,
+
+    #[argh(switch, description = "do not ask for confirmation")]
+    yes: bool,
+}
+impl DropAttr {
+    fn exec(self, dgraph: &Dgraph) -> Result<Dropped> {
+        confirm(
+            dgraph,
+            &format!("drop predicates {}", self.predicates.join(", ")),
+            self.yes,
+        )?;
+        drop_values(dgraph, "ATTR", &self.predicates)
     }
 }
 
 #[derive(FromArgs)]
-#[argh(subcommand, name = "get-health", description = "get status of nodes")]
-struct GetHealth {}
-
-#[derive(Deserialize, Debug)]
-struct HealthResponse {
-    // NOTE: this struct is not complete, it has only what's needed,
-    // actual response contains more data.
-    // see: https://dgraph.io/docs/graphql/admin
-    address: String,
-    status: String,
-    uptime: u64,
-}
-
-impl GetHealth {
-    fn exec(self, dgraph: &Dgraph) -> Result<()> {
-        let resp: Vec<HealthResponse> = dgraph.get("health").call()?.into_json()?;
-        for h in &resp {
-            println!(
-                "{} is {}, uptime: {}",
-                &h.address,
-                &h.status,
-                format_duration(Duration::new(h.uptime, 0)),
-            );
+#[argh(
+    subcommand,
+    name = "drop-type",
+    description = "drop types (keep predicates and data)"
+)]
+struct DropType {
+    #[argh(positional)]
+    types: Vec<String>,
+
+    #[argh(switch, description = "do not ask for confirmation")]
+    yes: bool,
+}
+impl DropType {
+    fn exec(self, dgraph: &Dgraph) -> Result<Dropped> {
+        confirm(
+            dgraph,
+            &format!("drop types {}", self.types.join(", ")),
+            self.yes,
+        )?;
+        drop_values(dgraph, "TYPE", &self.types)
+    }
+}
+
+#[derive(Serialize)]
+struct Dropped {
+    dropped: Vec<String>,
+}
+
+impl Report for Dropped {
+    fn print_text(&self) {
+        for value in &self.dropped {
+            println!("dropped {}", value);
         }
-        Ok(())
     }
 }
 
+// `drop_values` sends a separate drop operation for each value, because
+// alter accepts only one `drop_value` at a time.
+fn drop_values(dgraph: &Dgraph, op: &str, values: &[String]) -> Result<Dropped> {
+    if values.is_empty() {
+        return Err(anyhow!("nothing to drop"));
+    }
+    let dropped = dgraph.for_each_request(values, |value| {
+        dgraph
+            .alter(&json!({ "drop_op": op, "drop_value": value }).to_string())
+            .with_context(|| format!("could not drop {}", value))?;
+        Ok(value.clone())
+    })?;
+    Ok(Dropped { dropped })
+}
+
+// `confirm` asks to type the host name before doing something destructive,
+// so that it's harder to do it against the wrong cluster.
+// `yes` is ignored for protected profiles.
+fn confirm(dgraph: &Dgraph, action: &str, yes: bool) -> Result<()> {
+    if dgraph.is_dry_run() || (yes && !dgraph.is_protected()) {
+        return Ok(());
+    }
+    let host = dgraph.host();
+    eprintln!("this will {}", action);
+    eprintln!("  url:       {}", dgraph.base_url());
+    eprintln!("  namespace: {}", dgraph.namespace());
+    eprint!("type the host name ({}) to confirm: ", &host);
+    io::stderr().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    if input.trim() != host {
+        return Err(anyhow!("aborted"));
+    }
+    Ok(())
+}
+
 #[derive(FromArgs)]
 #[argh(subcommand)]
 enum SubCommand {
     UpdateSchema(UpdateSchema),
     GetSchema(GetSchema),
+    DiffSchema(DiffSchema),
+    GetDqlSchema(dql::GetDqlSchema),
+    UpdateDqlSchema(dql::UpdateDqlSchema),
     DropAll(DropAll),
     DropData(DropData),
-    GetHealth(GetHealth),
+    DropAttr(DropAttr),
+    DropType(DropType),
+    GetHealth(health::GetHealth),
+    ClusterState(state::ClusterState),
+    MoveTablet(tablet::MoveTablet),
+    Rebalance(tablet::Rebalance),
+    RemoveNode(cluster::RemoveNode),
+    Assign(cluster::Assign),
+    Draining(maintenance::Draining),
+    Shutdown(maintenance::Shutdown),
+    Config(runtime_config::Config),
+    License(license::License),
+    Export(export::Export),
+    Backup(backup::Backup),
+    Restore(backup::Restore),
+    ListBackups(backup::ListBackups),
+    User(acl::User),
+    Group(acl::Group),
+    Namespace(namespace::Namespace),
 }
 impl SubCommand {
-    fn exec(self, dgraph: &Dgraph) -> Result<()> {
+    // `exec` runs the command, prints its report and returns the exit code.
+    fn exec(self, dgraph: &Dgraph, format: Format) -> Result<i32> {
         match self {
-            SubCommand::UpdateSchema(x) => x.exec(dgraph),
-            SubCommand::GetSchema(x) => x.exec(dgraph),
-            SubCommand::DropAll(x) => x.exec(dgraph),
-            SubCommand::DropData(x) => x.exec(dgraph),
-            SubCommand::GetHealth(x) => x.exec(dgraph),
+            SubCommand::UpdateSchema(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::GetSchema(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::DiffSchema(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::GetDqlSchema(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::UpdateDqlSchema(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::DropAll(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::DropData(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::DropAttr(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::DropType(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::GetHealth(x) => x.run(dgraph, format),
+            SubCommand::ClusterState(x) => x.run(dgraph, format),
+            SubCommand::MoveTablet(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::Rebalance(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::RemoveNode(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::Assign(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::Draining(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::Shutdown(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::Config(x) => x.exec(dgraph, format),
+            SubCommand::License(x) => x.exec(dgraph, format),
+            SubCommand::Export(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::Backup(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::Restore(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::ListBackups(x) => output::print(format, &x.exec(dgraph)?),
+            SubCommand::User(x) => x.exec(dgraph, format),
+            SubCommand::Group(x) => x.exec(dgraph, format),
+            SubCommand::Namespace(x) => x.exec(dgraph, format),
         }
     }
 }
@@ -250,20 +451,201 @@ impl SubCommand {
 struct Args {
     #[argh(
         option,
-        description = "dgraph url",
-        default = "String::from(\"localhost:8080\")"
+        description = "dgraph url, can be set with DGRAPH_ADMIN_URL (default: localhost:8080)"
     )]
-    url: String,
+    url: Option<String>,
 
-    #[argh(option, description = "auth header to include with the request")]
+    #[argh(
+        option,
+        description = "profile from the config file to use, can be set with DGRAPH_ADMIN_PROFILE"
+    )]
+    profile: Option<String>,
+
+    #[argh(
+        option,
+        description = "auth header to include with the request, can be set with DGRAPH_ADMIN_AUTH"
+    )]
     auth: Option<String>,
 
+    #[argh(option, description = "file to read the auth header from")]
+    auth_file: Option<String>,
+
+    #[argh(
+        option,
+        description = "auth header to include with requests to zero, can be set with DGRAPH_ADMIN_ZERO_AUTH"
+    )]
+    zero_auth: Option<String>,
+
+    #[argh(option, description = "file to read the auth header for zero from")]
+    zero_auth_file: Option<String>,
+
+    #[argh(
+        option,
+        description = "acl user to log in as, can be set with DGRAPH_ADMIN_USER"
+    )]
+    user: Option<String>,
+
+    #[argh(
+        option,
+        description = "acl password, can be set with DGRAPH_ADMIN_PASSWORD"
+    )]
+    password: Option<String>,
+
+    #[argh(option, description = "file to read the acl password from")]
+    password_file: Option<String>,
+
+    #[argh(
+        option,
+        description = "namespace to log in to, schema and drop commands are scoped to it (default: 0)"
+    )]
+    namespace: Option<u64>,
+
+    #[argh(
+        switch,
+        description = "print requests that would change anything instead of sending them"
+    )]
+    dry_run: bool,
+
+    #[argh(
+        option,
+        description = "ca certificates to verify dgraph's certificate with (default: bundled mozilla roots)"
+    )]
+    tls_ca: Option<String>,
+
+    #[argh(option, description = "client certificate for mutual tls")]
+    tls_cert: Option<String>,
+
+    #[argh(option, description = "client certificate's private key")]
+    tls_key: Option<String>,
+
+    #[argh(
+        option,
+        description = "server name to verify dgraph's certificate against (default: url's host)"
+    )]
+    tls_server_name: Option<String>,
+
+    #[argh(
+        option,
+        from_str_fn(parse_duration),
+        description = "timeout for the whole request, like 30s (default: none)"
+    )]
+    timeout: Option<Duration>,
+
+    #[argh(
+        option,
+        from_str_fn(parse_duration),
+        description = "timeout for connecting to dgraph (default: 30s)"
+    )]
+    connect_timeout: Option<Duration>,
+
+    #[argh(
+        option,
+        default = "5",
+        description = "how many times to retry reads on connection errors and 5xx responses (default: 5)"
+    )]
+    retries: u32,
+
+    #[argh(
+        option,
+        default = "Format::Text",
+        from_str_fn(parse_format),
+        description = "output format, text, json or yaml (default: text)"
+    )]
+    output: Format,
+
     #[argh(subcommand)]
     subcommand: SubCommand,
 }
 
-fn main() -> Result<()> {
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    humantime::parse_duration(s).map_err(|err| format!("invalid duration {:?}: {}", s, err))
+}
+
+fn main() {
     let args: Args = argh::from_env();
-    let dgraph = Dgraph::new(args.url, args.auth)?;
-    args.subcommand.exec(&dgraph)
+    let format = args.output;
+    let code = run(args).unwrap_or_else(|err| {
+        output::print_error(format, &err);
+        output::EXIT_ERROR
+    });
+    process::exit(code);
+}
+
+fn run(args: Args) -> Result<i32> {
+    let profile_name = args
+        .profile
+        .or_else(|| env::var("DGRAPH_ADMIN_PROFILE").ok());
+    let profile = match &profile_name {
+        Some(name) => config::load_profile(name)?,
+        None => config::Profile::default(),
+    };
+    // command line takes precedence over the profile. environment variables
+    // are only used without a profile, mixing the two would send secrets of
+    // one cluster to another (or skip the protection of a profile).
+    let from_env = |var: &str| {
+        let value = secret::from_env(var);
+        match &profile_name {
+            Some(name) if value.is_some() => {
+                eprintln!(
+                    "warning: {} is ignored, because profile {:?} is selected",
+                    var, name
+                );
+                None
+            }
+            _ => value,
+        }
+    };
+    let url = args
+        .url
+        .or_else(|| from_env("DGRAPH_ADMIN_URL"))
+        .or(profile.url)
+        .unwrap_or_else(|| String::from("localhost:8080"));
+    let auth = secret::resolve("--auth", args.auth, args.auth_file)?
+        .or_else(|| from_env("DGRAPH_ADMIN_AUTH"))
+        .or(profile.auth);
+    let zero_auth = secret::resolve("--zero-auth", args.zero_auth, args.zero_auth_file)?
+        .or_else(|| from_env("DGRAPH_ADMIN_ZERO_AUTH"))
+        .or(profile.zero_auth);
+    let user = args
+        .user
+        .or_else(|| from_env("DGRAPH_ADMIN_USER"))
+        .or(profile.user);
+    let password = secret::resolve("--password", args.password, args.password_file)?
+        .or_else(|| from_env("DGRAPH_ADMIN_PASSWORD"))
+        .or(profile.password);
+    let namespace = args.namespace.or(profile.namespace);
+    let credentials = match (user, password) {
+        (Some(user), Some(password)) => Some(Credentials {
+            user,
+            password,
+            namespace,
+        }),
+        // access token is what scopes requests to a namespace, so there's
+        // no namespace without logging in.
+        (None, None) if namespace.is_some() => {
+            return Err(anyhow!("namespace requires user and password"))
+        }
+        (None, None) => None,
+        _ => return Err(anyhow!("user and password must be used together")),
+    };
+    let options = Options {
+        dry_run: args.dry_run,
+        protected: profile.protected,
+        tls: TlsOptions {
+            ca: args.tls_ca.or(profile.tls_ca),
+            cert: args.tls_cert.or(profile.tls_cert),
+            key: args.tls_key.or(profile.tls_key),
+            server_name: args.tls_server_name.or(profile.tls_server_name),
+        },
+        timeout: args.timeout,
+        connect_timeout: args.connect_timeout,
+        retries: args.retries,
+        zero_auth,
+    };
+    let dgraph = Dgraph::new(url, auth, credentials, options)?;
+    match args.subcommand.exec(&dgraph, args.output) {
+        // nothing was changed, what would have been is all there is to report
+        Err(err) if err.is::<DryRun>() => output::print(args.output, &dgraph.dry_run_report()),
+        result => result,
+    }
 }
@@ -0,0 +1,211 @@
+// draining mode and shutdown, for maintenance. both go to the alpha behind
+// `--url`, or, with `--all`, to every alpha of the cluster, one at a time.
+// see: https://dgraph.io/docs/deploy/dgraph-administration/
+
+use crate::{
+    confirm,
+    dgraph::{Dgraph, DryRun, Response},
+    health::get_nodes,
+    output::{Report, EXIT_ERROR},
+};
+use anyhow::{anyhow, Context, Result};
+use argh::FromArgs;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use url::Url;
+
+// alphas listen for http on their internal port + 1000 (7080 and 8080,
+// unless `--port_offset` is set).
+const HTTP_PORT_OFFSET: u16 = 1000;
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "draining",
+    description = "turn draining mode on or off, alphas in draining mode reject queries and mutations"
+)]
+pub struct Draining {
+    #[argh(positional, from_str_fn(parse_on_off), description = "on or off")]
+    enable: bool,
+
+    #[argh(
+        switch,
+        description = "every alpha of the cluster, not only the one behind --url"
+    )]
+    all: bool,
+}
+
+fn parse_on_off(s: &str) -> Result<bool, String> {
+    match s {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("expected on or off, got {:?}", s)),
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "shutdown",
+    description = "shut down alphas gracefully"
+)]
+pub struct Shutdown {
+    #[argh(
+        switch,
+        description = "every alpha of the cluster, not only the one behind --url"
+    )]
+    all: bool,
+
+    #[argh(switch, description = "do not ask for confirmation")]
+    yes: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct Payload {
+    response: Response,
+}
+
+// `AlphaResult` is what a single alpha said, failures don't stop the rest
+// of alphas from being called.
+#[derive(Serialize)]
+struct AlphaResult {
+    url: String,
+    message: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct AlphaResults {
+    alphas: Vec<AlphaResult>,
+}
+
+impl Report for AlphaResults {
+    fn print_text(&self) {
+        for alpha in &self.alphas {
+            match (&alpha.message, &alpha.error) {
+                (_, Some(error)) => println!("{}: error: {}", &alpha.url, error),
+                (Some(message), None) => println!("{}: {}", &alpha.url, message),
+                (None, None) => println!("{}", &alpha.url),
+            }
+        }
+    }
+
+    fn exit_code(&self) -> i32 {
+        if self.alphas.iter().any(|a| a.error.is_some()) {
+            EXIT_ERROR
+        } else {
+            0
+        }
+    }
+}
+
+impl Draining {
+    pub fn exec(self, dgraph: &Dgraph) -> Result<AlphaResults> {
+        #[derive(Deserialize, Debug)]
+        struct Data {
+            draining: Payload,
+        }
+        for_each_alpha(dgraph, self.all, |alpha| {
+            let resp = alpha.query::<_, Data>(
+                "admin",
+                r#"mutation draining($enable: Boolean) {
+                    draining(enable: $enable) {
+                        response { message }
+                    }
+                }"#,
+                json!({ "enable": self.enable }),
+            )?;
+            Ok(resp
+                .ok_or_else(|| anyhow!("empty response"))?
+                .draining
+                .response
+                .message)
+        })
+    }
+}
+
+impl Shutdown {
+    pub fn exec(self, dgraph: &Dgraph) -> Result<AlphaResults> {
+        #[derive(Deserialize, Debug)]
+        struct Data {
+            shutdown: Payload,
+        }
+        let action = if self.all {
+            "shut down every alpha of the cluster"
+        } else {
+            "shut down the alpha"
+        };
+        confirm(dgraph, action, self.yes)?;
+        for_each_alpha(dgraph, self.all, |alpha| {
+            let resp = alpha.query::<(), Data>(
+                "admin",
+                r#"mutation shutdown {
+                    shutdown {
+                        response { message }
+                    }
+                }"#,
+                (),
+            )?;
+            Ok(resp
+                .ok_or_else(|| anyhow!("empty response"))?
+                .shutdown
+                .response
+                .message)
+        })
+    }
+}
+
+// `for_each_alpha` calls `f` for the alpha behind the url, or for every alpha
+// listed by `/health?all`, in order of groups.
+pub fn for_each_alpha(
+    dgraph: &Dgraph,
+    all: bool,
+    f: impl Fn(&Dgraph) -> Result<String>,
+) -> Result<AlphaResults> {
+    let mut alphas = Vec::new();
+    if all {
+        let mut nodes = get_nodes(dgraph, true)?;
+        nodes.retain(|n| n.instance == "alpha");
+        nodes.sort_by(|a, b| (&a.group, &a.address).cmp(&(&b.group, &b.address)));
+        for node in nodes {
+            alphas.push(dgraph.with_url(&http_url(dgraph.base_url(), &node.address)?)?);
+        }
+    } else {
+        alphas.push(dgraph.with_url(dgraph.base_url())?);
+    }
+
+    let results = dgraph.for_each_request(&alphas, |alpha| {
+        let url = alpha.base_url().to_string();
+        match f(alpha) {
+            Ok(message) => Ok(AlphaResult {
+                url,
+                message: Some(message),
+                error: None,
+            }),
+            // failures don't stop the rest, but requests that were not sent
+            // are not failures
+            Err(err) if err.is::<DryRun>() => Err(err),
+            Err(err) => Ok(AlphaResult {
+                url,
+                message: None,
+                error: Some(format!("{:#}", err)),
+            }),
+        }
+    })?;
+    Ok(AlphaResults { alphas: results })
+}
+
+// `http_url` turns alpha's internal address, as reported by `/health?all`,
+// into its http url, with the same scheme as `base_url`.
+fn http_url(base_url: &str, address: &str) -> Result<String> {
+    let scheme = Url::parse(base_url)?.scheme().to_string();
+    let (host, port) = address
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+        .ok_or_else(|| anyhow!("unexpected alpha address {:?}", address))?;
+    let port = port
+        .checked_add(HTTP_PORT_OFFSET)
+        .with_context(|| format!("unexpected alpha address {:?}", address))?;
+    Ok(format!("{}://{}:{}", scheme, host, port))
+}
@@ -0,0 +1,225 @@
+// multi-tenancy namespaces management, requires logging in as a guardian of
+// the galaxy (a guardian of namespace 0).
+// see: https://dgraph.io/docs/enterprise-features/multitenancy/
+
+use crate::{
+    acl::required_password,
+    confirm,
+    dgraph::Dgraph,
+    output::{self, Format, Report},
+    secret,
+    table::print_table,
+};
+use anyhow::{anyhow, Result};
+use argh::FromArgs;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "namespace", description = "manage namespaces")]
+pub struct Namespace {
+    #[argh(subcommand)]
+    subcommand: NamespaceSubCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum NamespaceSubCommand {
+    Add(NamespaceAdd),
+    Delete(NamespaceDelete),
+    List(NamespaceList),
+    ResetPassword(NamespaceResetPassword),
+}
+
+impl Namespace {
+    pub fn exec(self, dgraph: &Dgraph, format: Format) -> Result<i32> {
+        match self.subcommand {
+            NamespaceSubCommand::Add(x) => output::print(format, &x.exec(dgraph)?),
+            NamespaceSubCommand::Delete(x) => output::print(format, &x.exec(dgraph)?),
+            NamespaceSubCommand::List(x) => output::print(format, &x.exec(dgraph)?),
+            NamespaceSubCommand::ResetPassword(x) => output::print(format, &x.exec(dgraph)?),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NamespacePayload {
+    namespace_id: u64,
+    message: String,
+}
+
+impl Report for NamespacePayload {
+    fn print_text(&self) {
+        println!("{} (id: {})", &self.message, self.namespace_id);
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "add", description = "add a namespace")]
+struct NamespaceAdd {
+    #[argh(
+        option,
+        description = "password of the namespace's groot user (default: password)"
+    )]
+    password: Option<String>,
+
+    #[argh(option, description = "file to read the groot password from")]
+    password_file: Option<String>,
+}
+impl NamespaceAdd {
+    fn exec(self, dgraph: &Dgraph) -> Result<NamespacePayload> {
+        let password = secret::resolve("--password", self.password, self.password_file)?;
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "camelCase")]
+        struct Data {
+            add_namespace: NamespacePayload,
+        }
+        let resp = dgraph.query::<_, Data>(
+            "admin",
+            r#"mutation addNamespace($input: AddNamespaceInput) {
+                addNamespace(input: $input) { namespaceId message }
+            }"#,
+            json!({ "input": { "password": password } }),
+        )?;
+        Ok(resp.ok_or_else(|| anyhow!("empty response"))?.add_namespace)
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "delete",
+    description = "delete a namespace with all of its data"
+)]
+struct NamespaceDelete {
+    #[argh(positional)]
+    id: u64,
+
+    #[argh(switch, description = "do not ask for confirmation")]
+    yes: bool,
+}
+impl NamespaceDelete {
+    fn exec(self, dgraph: &Dgraph) -> Result<NamespacePayload> {
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "camelCase")]
+        struct Data {
+            delete_namespace: NamespacePayload,
+        }
+        if self.id == 0 {
+            return Err(anyhow!("namespace 0 can not be deleted"));
+        }
+        confirm(
+            dgraph,
+            &format!("delete namespace {} with all of its data", self.id),
+            self.yes,
+        )?;
+        let resp = dgraph.query::<_, Data>(
+            "admin",
+            r#"mutation deleteNamespace($input: NamespaceInput!) {
+                deleteNamespace(input: $input) { namespaceId message }
+            }"#,
+            json!({ "input": { "namespaceId": self.id } }),
+        )?;
+        Ok(resp
+            .ok_or_else(|| anyhow!("empty response"))?
+            .delete_namespace)
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "list", description = "list namespaces")]
+struct NamespaceList {}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+struct Namespaces(Vec<u64>);
+
+impl Report for Namespaces {
+    fn print_text(&self) {
+        let rows: Vec<Vec<String>> = self.0.iter().map(|ns| vec![ns.to_string()]).collect();
+        print_table(&["NAMESPACE"], &rows);
+    }
+}
+
+impl NamespaceList {
+    fn exec(self, dgraph: &Dgraph) -> Result<Namespaces> {
+        #[derive(Deserialize, Debug)]
+        struct State {
+            namespaces: Option<Vec<u64>>,
+        }
+        #[derive(Deserialize, Debug)]
+        struct Data {
+            state: State,
+        }
+        let resp = dgraph.query::<_, Data>(
+            "admin",
+            r#"query state {
+                state { namespaces }
+            }"#,
+            (),
+        )?;
+        let mut namespaces = resp
+            .and_then(|data| data.state.namespaces)
+            .unwrap_or_default();
+        namespaces.sort_unstable();
+        Ok(Namespaces(namespaces))
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "reset-password",
+    description = "reset password of a user in a namespace"
+)]
+struct NamespaceResetPassword {
+    #[argh(positional)]
+    namespace: u64,
+
+    #[argh(positional, default = "String::from(\"groot\")")]
+    user: String,
+
+    #[argh(option, description = "new password")]
+    password: Option<String>,
+
+    #[argh(option, description = "file to read the new password from")]
+    password_file: Option<String>,
+}
+#[derive(Deserialize, Serialize, Debug)]
+struct ResetPasswordPayload {
+    message: String,
+}
+
+impl Report for ResetPasswordPayload {
+    fn print_text(&self) {
+        println!("{}", &self.message);
+    }
+}
+
+impl NamespaceResetPassword {
+    fn exec(self, dgraph: &Dgraph) -> Result<ResetPasswordPayload> {
+        let password = required_password(self.password, self.password_file)?;
+        #[derive(Deserialize, Debug)]
+        #[serde(rename_all = "camelCase")]
+        struct Data {
+            reset_password: ResetPasswordPayload,
+        }
+        let resp = dgraph.query::<_, Data>(
+            "admin",
+            r#"mutation resetPassword($input: ResetPasswordInput!) {
+                resetPassword(input: $input) { message }
+            }"#,
+            json!({
+                "input": {
+                    "userId": &self.user,
+                    "password": password,
+                    "namespace": self.namespace,
+                }
+            }),
+        )?;
+        Ok(resp
+            .ok_or_else(|| anyhow!("empty response"))?
+            .reset_password)
+    }
+}
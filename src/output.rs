@@ -0,0 +1,321 @@
+// output of commands, in the format selected with `--output`.
+
+use crate::dgraph::GqlErrors;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+
+// exit codes, in addition to 0 for success:
+// the command failed
+pub const EXIT_ERROR: i32 = 1;
+// the command worked, but what it checked did not pass
+pub const EXIT_CHECK_FAILED: i32 = 2;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Yaml,
+}
+
+pub fn parse_format(s: &str) -> Result<Format, String> {
+    match s {
+        "text" => Ok(Format::Text),
+        "json" => Ok(Format::Json),
+        "yaml" => Ok(Format::Yaml),
+        _ => Err(format!(
+            "unknown output {:?}, expected text, json or yaml",
+            s
+        )),
+    }
+}
+
+// `Report` is what a command has to say when it's done. it's serialized as
+// is for json and yaml, so field names are a part of the interface.
+pub trait Report: Serialize {
+    // `print_text` prints the report for humans.
+    fn print_text(&self);
+
+    fn exit_code(&self) -> i32 {
+        0
+    }
+}
+
+// `print` prints the report and returns the exit code.
+pub fn print<R: Report>(format: Format, report: &R) -> Result<i32> {
+    match format {
+        Format::Text => report.print_text(),
+        Format::Json => println!("{}", serde_json::to_string_pretty(report)?),
+        Format::Yaml => print!("{}", to_yaml(&serde_json::to_value(report)?)),
+    }
+    Ok(report.exit_code())
+}
+
+// `print_error` prints `{"errors": [...]}`, with graphql errors as dgraph
+// returned them.
+pub fn print_error(format: Format, err: &anyhow::Error) {
+    let errors = match err.downcast_ref::<GqlErrors>() {
+        Some(gql) => json!(gql.errors),
+        None => json!([{ "message": format!("{:#}", err) }]),
+    };
+    let report = json!({ "errors": errors });
+    match format {
+        Format::Text => eprintln!("Error: {:?}", err),
+        Format::Json => println!("{:#}", report),
+        Format::Yaml => print!("{}", to_yaml(&report)),
+    }
+}
+
+// `Done` is reported by commands that have nothing to say but "success".
+#[derive(Serialize)]
+pub struct Done {
+    success: bool,
+}
+
+pub const DONE: Done = Done { success: true };
+
+impl Report for Done {
+    fn print_text(&self) {
+        println!("success");
+    }
+}
+
+// `to_yaml` renders json as block style yaml. strings are quoted whenever
+// they could be mistaken for something else, multiline strings (such as
+// schemas) are written as literal blocks.
+fn to_yaml(value: &JsonValue) -> String {
+    let mut out = String::new();
+    match value {
+        JsonValue::Object(map) if !map.is_empty() => write_map(&mut out, map, 0),
+        JsonValue::Array(items) if !items.is_empty() => write_seq(&mut out, items, 0),
+        value => {
+            out.push_str(&scalar(value));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn write_map(out: &mut String, map: &Map<String, JsonValue>, indent: usize) {
+    for (key, value) in map {
+        out.push_str(&" ".repeat(indent));
+        out.push_str(&string(key));
+        out.push(':');
+        write_node(out, value, indent + 2);
+    }
+}
+
+fn write_seq(out: &mut String, items: &[JsonValue], indent: usize) {
+    for item in items {
+        out.push_str(&" ".repeat(indent));
+        out.push('-');
+        // nested collections start on the same line as the dash:
+        // `- key: value`
+        let mut nested = String::new();
+        match item {
+            JsonValue::Object(map) if !map.is_empty() => write_map(&mut nested, map, indent + 2),
+            JsonValue::Array(items) if !items.is_empty() => {
+                write_seq(&mut nested, items, indent + 2)
+            }
+            item => {
+                write_node(out, item, indent + 2);
+                continue;
+            }
+        }
+        out.push(' ');
+        out.push_str(&nested[indent + 2..]);
+    }
+}
+
+// `write_node` writes a value after `key:` or `-`.
+fn write_node(out: &mut String, value: &JsonValue, indent: usize) {
+    match value {
+        JsonValue::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            write_map(out, map, indent);
+        }
+        JsonValue::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            write_seq(out, items, indent);
+        }
+        JsonValue::String(s) if is_block(s) => {
+            // chomping indicator: `-` strips the final line break, `+` keeps
+            // all of them
+            let chomping = match s.len() - s.trim_end_matches('\n').len() {
+                0 => "-",
+                1 => "",
+                _ => "+",
+            };
+            out.push_str(" |");
+            out.push_str(chomping);
+            out.push('\n');
+            for line in s.trim_end_matches('\n').split('\n') {
+                if !line.is_empty() {
+                    out.push_str(&" ".repeat(indent));
+                    out.push_str(line);
+                }
+                out.push('\n');
+            }
+            for _ in 1..s.len() - s.trim_end_matches('\n').len() {
+                out.push('\n');
+            }
+        }
+        value => {
+            out.push(' ');
+            out.push_str(&scalar(value));
+            out.push('\n');
+        }
+    }
+}
+
+// `is_block` checks if the string is better written as a literal block.
+// leading whitespace would need an indentation indicator, so such strings
+// are quoted instead.
+fn is_block(s: &str) -> bool {
+    s.contains('\n')
+        && !s.starts_with(&[' ', '\t', '\n'][..])
+        && !s.chars().any(|c| c.is_control() && c != '\n' && c != '\t')
+}
+
+fn scalar(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => string(s),
+        JsonValue::Object(_) => String::from("{}"),
+        JsonValue::Array(_) => String::from("[]"),
+        value => value.to_string(),
+    }
+}
+
+// `string` leaves the string plain if it's unambiguous, otherwise quotes it
+// the json way, which yaml understands as well.
+fn string(s: &str) -> String {
+    let plain = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || " _-./:@,()".contains(c))
+        && !s.starts_with(|c: char| c.is_ascii_digit() || " -.:@,".contains(c))
+        && !s.ends_with(' ')
+        && !s.ends_with(':')
+        && !s.contains(": ")
+        && !matches!(
+            s.to_lowercase().as_str(),
+            "true" | "false" | "yes" | "no" | "on" | "off" | "y" | "n" | "null" | "nan" | "inf"
+        );
+    if plain {
+        s.to_string()
+    } else {
+        JsonValue::String(s.to_string()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_ambiguous_strings() {
+        let cases = [
+            ("plain", "plain"),
+            ("two words", "two words"),
+            ("alpha1:7080", "alpha1:7080"),
+            ("http://example.com/a", "http://example.com/a"),
+            ("", r#""""#),
+            // would be booleans or null
+            ("true", r#""true""#),
+            ("No", r#""No""#),
+            ("on", r#""on""#),
+            ("y", r#""y""#),
+            ("null", r#""null""#),
+            ("~", r#""~""#),
+            // would be numbers
+            ("10", r#""10""#),
+            ("1.5", r#""1.5""#),
+            ("-1", r#""-1""#),
+            ("+1", r#""+1""#),
+            ("0x1f", r#""0x1f""#),
+            (".inf", r#"".inf""#),
+            ("NaN", r#""NaN""#),
+            // would be a map, a comment or something else
+            ("key: value", r#""key: value""#),
+            ("ends with:", r#""ends with:""#),
+            (":start", r#"":start""#),
+            ("a # comment", r##""a # comment""##),
+            ("#", r##""#""##),
+            ("- item", r#""- item""#),
+            ("@id", r#""@id""#),
+            ("[list]", r#""[list]""#),
+            ("{map}", r#""{map}""#),
+            ("'single'", r#""'single'""#),
+            ("\"double\"", r#""\"double\"""#),
+            ("&anchor", r#""&anchor""#),
+            ("*alias", r#""*alias""#),
+            ("!tag", r#""!tag""#),
+            ("|", r#""|""#),
+            (" leading", r#"" leading""#),
+            ("trailing ", r#""trailing ""#),
+            ("tab\there", r#""tab\there""#),
+        ];
+        for (s, expected) in &cases {
+            assert_eq!(&string(s), expected, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(
+            to_yaml(&json!({"a": 1, "b": -2.5, "c": true, "d": null, "e": "10"})),
+            "a: 1\nb: -2.5\nc: true\nd: null\ne: \"10\"\n"
+        );
+        assert_eq!(to_yaml(&json!("top")), "top\n");
+        assert_eq!(to_yaml(&json!(null)), "null\n");
+        assert_eq!(to_yaml(&json!({"true": 1})), "\"true\": 1\n");
+    }
+
+    #[test]
+    fn multiline_strings() {
+        assert_eq!(
+            to_yaml(&json!({"schema": "type A {\n  x: Int\n}\n"})),
+            "schema: |\n  type A {\n    x: Int\n  }\n"
+        );
+        // without the final line break
+        assert_eq!(to_yaml(&json!({"s": "a\nb"})), "s: |-\n  a\n  b\n");
+        // with more than one
+        assert_eq!(to_yaml(&json!({"s": "a\nb\n\n"})), "s: |+\n  a\n  b\n\n");
+        // empty lines are not indented
+        assert_eq!(to_yaml(&json!({"s": "a\n\nb\n"})), "s: |\n  a\n\n  b\n");
+        // leading whitespace would need an indentation indicator
+        assert_eq!(to_yaml(&json!({"s": " a\nb"})), "s: \" a\\nb\"\n");
+        assert_eq!(to_yaml(&json!({"s": "\na"})), "s: \"\\na\"\n");
+        assert_eq!(to_yaml(&json!({"s": "a\r\nb"})), "s: \"a\\r\\nb\"\n");
+        assert_eq!(to_yaml(&json!([{"s": "a\nb\n"}])), "- s: |\n    a\n    b\n");
+    }
+
+    #[test]
+    fn empty_collections() {
+        assert_eq!(to_yaml(&json!({})), "{}\n");
+        assert_eq!(to_yaml(&json!([])), "[]\n");
+        assert_eq!(
+            to_yaml(&json!({"a": {}, "b": [], "c": [{}, []]})),
+            "a: {}\nb: []\nc:\n  - {}\n  - []\n"
+        );
+    }
+
+    #[test]
+    fn nested_collections() {
+        assert_eq!(
+            to_yaml(&json!({"a": {"b": {"c": 1}}, "d": [1, 2]})),
+            "a:\n  b:\n    c: 1\nd:\n  - 1\n  - 2\n"
+        );
+        assert_eq!(
+            to_yaml(&json!([{"name": "x", "groups": ["a", "b"]}, {"name": "z"}])),
+            "- groups:\n    - a\n    - b\n  name: x\n- name: z\n"
+        );
+        assert_eq!(
+            to_yaml(&json!([[1, 2], [[3], 4], []])),
+            "- - 1\n  - 2\n- - - 3\n  - 4\n- []\n"
+        );
+        assert_eq!(
+            to_yaml(&json!({"a": [[{"b": 1, "c": [2]}]]})),
+            "a:\n  - - b: 1\n      c:\n        - 2\n"
+        );
+    }
+}
@@ -0,0 +1,259 @@
+// configuration of a running alpha, which can be changed without restarting
+// it. not to be confused with the config file of this tool.
+// see: https://dgraph.io/docs/graphql/admin/#using-the-config-mutation
+
+use crate::{
+    dgraph::{Dgraph, Response},
+    maintenance::{for_each_alpha, AlphaResults},
+    output::{self, Format, Report},
+};
+use anyhow::{anyhow, Result};
+use argh::FromArgs;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as JsonValue};
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "config",
+    description = "get or change runtime configuration of alphas"
+)]
+pub struct Config {
+    #[argh(subcommand)]
+    subcommand: ConfigSubCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum ConfigSubCommand {
+    Get(ConfigGet),
+    Set(ConfigSet),
+}
+
+impl Config {
+    pub fn exec(self, dgraph: &Dgraph, format: Format) -> Result<i32> {
+        match self.subcommand {
+            ConfigSubCommand::Get(x) => output::print(format, &x.exec(dgraph)?),
+            ConfigSubCommand::Set(x) => output::print(format, &x.exec(dgraph)?),
+        }
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "get",
+    description = "get runtime configuration of the alpha"
+)]
+struct ConfigGet {}
+
+// field names are the same as in `config` query. which fields there are
+// depends on the version of dgraph, the rest are `None`.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RuntimeConfig {
+    cache_mb: Option<f64>,
+    // since v21.12
+    #[serde(rename = "logDQLRequest")]
+    log_dql_request: Option<bool>,
+    // before v21.12
+    log_request: Option<bool>,
+}
+
+impl Report for RuntimeConfig {
+    fn print_text(&self) {
+        let show = |value: Option<String>| value.unwrap_or_else(|| String::from("-"));
+        println!(
+            "cache-mb     {}",
+            show(self.cache_mb.map(|v| v.to_string()))
+        );
+        println!(
+            "log-dql      {}",
+            show(self.log_dql_request.map(|v| v.to_string()))
+        );
+        println!(
+            "log-request  {}",
+            show(self.log_request.map(|v| v.to_string()))
+        );
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ConfigData {
+    config: RuntimeConfig,
+}
+
+impl ConfigGet {
+    fn exec(self, dgraph: &Dgraph) -> Result<RuntimeConfig> {
+        let known = dgraph.type_fields("Config")?;
+        let fields: Vec<&str> = ["cacheMb", "logDQLRequest", "logRequest"]
+            .iter()
+            .copied()
+            .filter(|field| known.iter().any(|k| k == field))
+            .collect();
+        if fields.is_empty() {
+            return Err(anyhow!("alpha doesn't report its runtime configuration"));
+        }
+        let resp = dgraph.query::<(), ConfigData>(
+            "admin",
+            &format!("query config {{ config {{ {} }} }}", fields.join(" ")),
+            (),
+        )?;
+        Ok(resp.ok_or_else(|| anyhow!("empty response"))?.config)
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "set",
+    description = "change runtime configuration of alphas, until they restart"
+)]
+struct ConfigSet {
+    #[argh(option, description = "size of caches, in megabytes")]
+    cache_mb: Option<f64>,
+
+    #[argh(
+        option,
+        description = "log dql queries and mutations, true or false (the same as --log-request before v21.12)"
+    )]
+    log_dql: Option<bool>,
+
+    #[argh(
+        option,
+        description = "log all requests, true or false (the same as --log-dql since v21.12)"
+    )]
+    log_request: Option<bool>,
+
+    #[argh(
+        switch,
+        description = "every alpha of the cluster, not only the one behind --url"
+    )]
+    all: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct ConfigPayload {
+    response: Response,
+}
+
+#[derive(Deserialize, Debug)]
+struct ConfigSetData {
+    config: ConfigPayload,
+}
+
+impl ConfigSet {
+    fn exec(self, dgraph: &Dgraph) -> Result<AlphaResults> {
+        if self.cache_mb.is_none() && self.log_dql.is_none() && self.log_request.is_none() {
+            return Err(anyhow!(
+                "nothing to set, use --cache-mb, --log-dql or --log-request"
+            ));
+        }
+        for_each_alpha(dgraph, self.all, |alpha| {
+            // alphas of a cluster that is being upgraded may differ
+            let input = self.input(&alpha.type_fields("ConfigInput")?)?;
+            let resp = alpha.query::<_, ConfigSetData>(
+                "admin",
+                r#"mutation config($input: ConfigInput!) {
+                    config(input: $input) {
+                        response { message }
+                    }
+                }"#,
+                json!({ "input": input }),
+            )?;
+            Ok(resp
+                .ok_or_else(|| anyhow!("empty response"))?
+                .config
+                .response
+                .message)
+        })
+    }
+
+    // `input` is what to set on an alpha that knows about `known` fields of
+    // `ConfigInput`, only what is given, the rest stays as is. logging
+    // requests is `logRequest` before v21.12 and `logDQLRequest` since, so
+    // either flag goes to whichever of the two the alpha has, the matching
+    // one wins if both are given.
+    fn input(&self, known: &[String]) -> Result<JsonValue> {
+        let has = |field: &str| known.iter().any(|k| k == field);
+        let mut input = Map::new();
+        if let Some(cache_mb) = self.cache_mb {
+            input.insert(String::from("cacheMb"), json!(cache_mb));
+        }
+        if self.log_dql.is_some() || self.log_request.is_some() {
+            let (field, value) = if has("logDQLRequest") {
+                ("logDQLRequest", self.log_dql.or(self.log_request))
+            } else if has("logRequest") {
+                ("logRequest", self.log_request.or(self.log_dql))
+            } else {
+                return Err(anyhow!("alpha can't log requests at runtime"));
+            };
+            input.insert(String::from(field), json!(value));
+        }
+        Ok(JsonValue::Object(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(cache_mb: Option<f64>, log_dql: Option<bool>, log_request: Option<bool>) -> ConfigSet {
+        ConfigSet {
+            cache_mb,
+            log_dql,
+            log_request,
+            all: false,
+        }
+    }
+
+    fn known(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn picks_the_field_alpha_has() {
+        let old = known(&["cacheMb", "logRequest"]);
+        let new = known(&["cacheMb", "logDQLRequest"]);
+        let both = set(Some(10.0), Some(true), Some(false));
+        assert_eq!(
+            both.input(&old).unwrap(),
+            json!({ "cacheMb": 10.0, "logRequest": false })
+        );
+        assert_eq!(
+            both.input(&new).unwrap(),
+            json!({ "cacheMb": 10.0, "logDQLRequest": true })
+        );
+        // either flag works on either version
+        assert_eq!(
+            set(None, Some(true), None).input(&old).unwrap(),
+            json!({ "logRequest": true })
+        );
+        assert_eq!(
+            set(None, None, Some(true)).input(&new).unwrap(),
+            json!({ "logDQLRequest": true })
+        );
+    }
+
+    #[test]
+    fn only_what_is_given() {
+        let new = known(&["cacheMb", "logDQLRequest"]);
+        assert_eq!(
+            set(Some(1.5), None, None).input(&new).unwrap(),
+            json!({ "cacheMb": 1.5 })
+        );
+        // an alpha that can't log requests can still have its caches resized
+        let none = known(&["cacheMb"]);
+        assert_eq!(
+            set(Some(1.5), None, None).input(&none).unwrap(),
+            json!({ "cacheMb": 1.5 })
+        );
+        assert_eq!(
+            set(Some(1.5), Some(true), None)
+                .input(&none)
+                .unwrap_err()
+                .to_string(),
+            "alpha can't log requests at runtime"
+        );
+    }
+}
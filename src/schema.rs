@@ -0,0 +1,1257 @@
+// a tiny graphql sdl parser, just enough to compare dgraph graphql schemas
+// structurally (field order, whitespace and comments don't matter).
+// spec: https://spec.graphql.org/June2018/#sec-Type-System
+
+use anyhow::{anyhow, Result};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Punct(char),
+    Spread,
+    Str(String),
+    Number(String),
+}
+
+struct Lexer<'s> {
+    chars: std::iter::Peekable<std::str::Chars<'s>>,
+    line: usize,
+}
+
+impl<'s> Lexer<'s> {
+    fn new(src: &'s str) -> Self {
+        Self {
+            chars: src.chars().peekable(),
+            line: 1,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn tokens(mut self) -> Result<Vec<(Token, usize)>> {
+        let mut tokens = Vec::new();
+        while let Some(&c) = self.chars.peek() {
+            let line = self.line;
+            match c {
+                // commas are insignificant in graphql
+                c if c.is_whitespace() || c == ',' || c == '\u{feff}' => {
+                    self.bump();
+                }
+                '#' => {
+                    while !matches!(self.bump(), Some('\n') | None) {}
+                }
+                '.' => {
+                    for _ in 0..3 {
+                        if self.bump() != Some('.') {
+                            return Err(anyhow!("line {}: expected `...`", line));
+                        }
+                    }
+                    tokens.push((Token::Spread, line));
+                }
+                '"' => tokens.push((Token::Str(self.string()?), line)),
+                c if c == '-' || c.is_ascii_digit() => {
+                    let mut num = String::new();
+                    while let Some(&c) = self.chars.peek() {
+                        if !(c.is_ascii_alphanumeric() || c == '-' || c == '+' || c == '.') {
+                            break;
+                        }
+                        num.push(c);
+                        self.bump();
+                    }
+                    tokens.push((Token::Number(num), line));
+                }
+                c if c == '_' || c.is_ascii_alphabetic() => {
+                    let mut name = String::new();
+                    while let Some(&c) = self.chars.peek() {
+                        if !(c == '_' || c.is_ascii_alphanumeric()) {
+                            break;
+                        }
+                        name.push(c);
+                        self.bump();
+                    }
+                    tokens.push((Token::Name(name), line));
+                }
+                '!' | '$' | '(' | ')' | ':' | '=' | '@' | '[' | ']' | '{' | '}' | '|' | '&' => {
+                    self.bump();
+                    tokens.push((Token::Punct(c), line));
+                }
+                c => return Err(anyhow!("line {}: unexpected character {:?}", line, c)),
+            }
+        }
+        Ok(tokens)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let line = self.line;
+        self.bump();
+        let mut s = String::new();
+        // block string, `"""..."""`
+        if self.chars.peek() == Some(&'"') {
+            self.bump();
+            if self.chars.peek() != Some(&'"') {
+                // it was just an empty string
+                return Ok(s);
+            }
+            self.bump();
+            let mut quotes = 0;
+            loop {
+                match self.bump() {
+                    Some('"') => {
+                        quotes += 1;
+                        if quotes == 3 {
+                            s.truncate(s.len() - 2);
+                            return Ok(s.trim().to_string());
+                        }
+                        s.push('"');
+                    }
+                    Some(c) => {
+                        quotes = 0;
+                        s.push(c);
+                    }
+                    None => return Err(anyhow!("line {}: unterminated block string", line)),
+                }
+            }
+        }
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some(c) => s.push(c),
+                    None => break,
+                },
+                Some('\n') | None => break,
+                Some(c) => s.push(c),
+            }
+        }
+        Err(anyhow!("line {}: unterminated string", line))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeRef {
+    Named(String),
+    List(Box<TypeRef>),
+    NonNull(Box<TypeRef>),
+}
+
+impl fmt::Display for TypeRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeRef::Named(name) => write!(f, "{}", name),
+            TypeRef::List(inner) => write!(f, "[{}]", inner),
+            TypeRef::NonNull(inner) => write!(f, "{}!", inner),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    // ints, floats, strings (quoted), booleans, enums, null and variables
+    Scalar(String),
+    List(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Scalar(s) => write!(f, "{}", s),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, " {}: {}", k, v)?;
+                }
+                write!(f, " }}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Directive {
+    pub name: String,
+    pub args: BTreeMap<String, Value>,
+}
+
+impl Directive {
+    // `search_tokenizers` returns tokenizers from `@search(by: [...])`.
+    // `@search` without arguments means "default index for the field type",
+    // which is represented as "default".
+    pub fn search_tokenizers(&self) -> BTreeSet<String> {
+        match self.args.get("by") {
+            Some(Value::List(items)) => items.iter().map(|v| v.to_string()).collect(),
+            Some(v) => std::iter::once(v.to_string()).collect(),
+            None => std::iter::once("default".to_string()).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Directive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "@{}", self.name)?;
+        if !self.args.is_empty() {
+            write!(f, "(")?;
+            for (i, (k, v)) in self.args.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}: {}", k, v)?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+type Directives = BTreeMap<String, Directive>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Type,
+    Interface,
+    Input,
+    Enum,
+    Union,
+    Scalar,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Type => "type",
+            Kind::Interface => "interface",
+            Kind::Input => "input",
+            Kind::Enum => "enum",
+            Kind::Union => "union",
+            Kind::Scalar => "scalar",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub ty: TypeRef,
+    // NOTE: default values of arguments and input fields are not kept,
+    // dgraph schemas barely use them.
+    pub args: BTreeMap<String, TypeRef>,
+    pub directives: Directives,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeDef {
+    pub kind: Kind,
+    pub interfaces: BTreeSet<String>,
+    pub directives: Directives,
+    pub fields: BTreeMap<String, Field>,
+    // enum values or union members
+    pub members: BTreeSet<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Schema {
+    pub types: BTreeMap<String, TypeDef>,
+}
+
+impl Schema {
+    pub fn parse(src: &str) -> Result<Self> {
+        let tokens = Lexer::new(src).tokens()?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let mut schema = Schema::default();
+        while !parser.at_end() {
+            parser.definition(&mut schema)?;
+        }
+        Ok(schema)
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map(|(_, line)| *line)
+            .unwrap_or(1)
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T> {
+        match self.peek() {
+            Some(t) => Err(anyhow!(
+                "line {}: expected {}, found {:?}",
+                self.line(),
+                expected,
+                t
+            )),
+            None => Err(anyhow!("unexpected end of schema, expected {}", expected)),
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.peek().cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn is_punct(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punct(c))
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        if self.is_punct(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<()> {
+        if self.eat_punct(c) {
+            Ok(())
+        } else {
+            self.error(&format!("`{}`", c))
+        }
+    }
+
+    fn name(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(Token::Name(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => self.error("a name"),
+        }
+    }
+
+    fn skip_description(&mut self) {
+        if let Some(Token::Str(_)) = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn definition(&mut self, schema: &mut Schema) -> Result<()> {
+        self.skip_description();
+        let keyword = self.name()?;
+        let extend = keyword == "extend";
+        let keyword = if extend { self.name()? } else { keyword };
+        let kind = match keyword.as_str() {
+            "type" => Kind::Type,
+            "interface" => Kind::Interface,
+            "input" => Kind::Input,
+            "enum" => Kind::Enum,
+            "union" => Kind::Union,
+            "scalar" => Kind::Scalar,
+            "schema" => {
+                self.directives()?;
+                if self.is_punct('{') {
+                    self.skip_block()?;
+                }
+                return Ok(());
+            }
+            "directive" => return self.skip_directive_definition(),
+            _ => {
+                self.pos -= 1;
+                return self.error("a type system definition");
+            }
+        };
+        let name = self.name()?;
+        let mut def = TypeDef {
+            kind,
+            interfaces: BTreeSet::new(),
+            directives: BTreeMap::new(),
+            fields: BTreeMap::new(),
+            members: BTreeSet::new(),
+        };
+        if self.peek() == Some(&Token::Name("implements".to_string())) {
+            self.pos += 1;
+            self.eat_punct('&');
+            def.interfaces.insert(self.name()?);
+            while self.eat_punct('&') {
+                def.interfaces.insert(self.name()?);
+            }
+        }
+        def.directives = self.directives()?;
+        match kind {
+            Kind::Type | Kind::Interface | Kind::Input => {
+                if self.eat_punct('{') {
+                    while !self.eat_punct('}') {
+                        self.skip_description();
+                        let field_name = self.name()?;
+                        let field = self.field()?;
+                        def.fields.insert(field_name, field);
+                    }
+                }
+            }
+            Kind::Enum => {
+                if self.eat_punct('{') {
+                    while !self.eat_punct('}') {
+                        self.skip_description();
+                        def.members.insert(self.name()?);
+                        self.directives()?;
+                    }
+                }
+            }
+            Kind::Union => {
+                if self.eat_punct('=') {
+                    self.eat_punct('|');
+                    def.members.insert(self.name()?);
+                    while self.eat_punct('|') {
+                        def.members.insert(self.name()?);
+                    }
+                }
+            }
+            Kind::Scalar => {}
+        }
+        match schema.types.get_mut(&name) {
+            Some(existing) if extend => {
+                existing.interfaces.extend(def.interfaces);
+                existing.directives.extend(def.directives);
+                existing.fields.extend(def.fields);
+                existing.members.extend(def.members);
+            }
+            _ => {
+                schema.types.insert(name, def);
+            }
+        }
+        Ok(())
+    }
+
+    fn field(&mut self) -> Result<Field> {
+        let mut args = BTreeMap::new();
+        if self.eat_punct('(') {
+            while !self.eat_punct(')') {
+                self.skip_description();
+                let arg_name = self.name()?;
+                self.expect_punct(':')?;
+                args.insert(arg_name, self.type_ref()?);
+                if self.eat_punct('=') {
+                    self.value()?;
+                }
+                self.directives()?;
+            }
+        }
+        self.expect_punct(':')?;
+        let ty = self.type_ref()?;
+        if self.eat_punct('=') {
+            self.value()?;
+        }
+        let directives = self.directives()?;
+        Ok(Field {
+            ty,
+            args,
+            directives,
+        })
+    }
+
+    fn type_ref(&mut self) -> Result<TypeRef> {
+        let ty = if self.eat_punct('[') {
+            let inner = self.type_ref()?;
+            self.expect_punct(']')?;
+            TypeRef::List(Box::new(inner))
+        } else {
+            TypeRef::Named(self.name()?)
+        };
+        if self.eat_punct('!') {
+            Ok(TypeRef::NonNull(Box::new(ty)))
+        } else {
+            Ok(ty)
+        }
+    }
+
+    fn directives(&mut self) -> Result<Directives> {
+        let mut directives = BTreeMap::new();
+        while self.eat_punct('@') {
+            let name = self.name()?;
+            let mut args = BTreeMap::new();
+            if self.eat_punct('(') {
+                while !self.eat_punct(')') {
+                    let arg_name = self.name()?;
+                    self.expect_punct(':')?;
+                    args.insert(arg_name, self.value()?);
+                }
+            }
+            directives.insert(name.clone(), Directive { name, args });
+        }
+        Ok(directives)
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        match self.next() {
+            Some(Token::Name(name)) => Ok(Value::Scalar(name)),
+            Some(Token::Number(num)) => Ok(Value::Scalar(num)),
+            Some(Token::Str(s)) => Ok(Value::Scalar(serde_json::to_string(&s)?)),
+            Some(Token::Punct('$')) => Ok(Value::Scalar(format!("${}", self.name()?))),
+            Some(Token::Punct('[')) => {
+                let mut items = Vec::new();
+                while !self.eat_punct(']') {
+                    items.push(self.value()?);
+                }
+                Ok(Value::List(items))
+            }
+            Some(Token::Punct('{')) => {
+                let mut fields = BTreeMap::new();
+                while !self.eat_punct('}') {
+                    let key = self.name()?;
+                    self.expect_punct(':')?;
+                    fields.insert(key, self.value()?);
+                }
+                Ok(Value::Object(fields))
+            }
+            _ => {
+                self.pos -= 1;
+                self.error("a value")
+            }
+        }
+    }
+
+    fn skip_block(&mut self) -> Result<()> {
+        self.expect_punct('{')?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some(Token::Punct('{')) => depth += 1,
+                Some(Token::Punct('}')) => depth -= 1,
+                Some(_) => {}
+                None => return self.error("`}`"),
+            }
+        }
+        Ok(())
+    }
+
+    // directive @name(args) repeatable? on A | B
+    fn skip_directive_definition(&mut self) -> Result<()> {
+        self.expect_punct('@')?;
+        self.name()?;
+        if self.is_punct('(') {
+            self.field_args_only()?;
+        }
+        if self.peek() == Some(&Token::Name("repeatable".to_string())) {
+            self.pos += 1;
+        }
+        if self.name()? != "on" {
+            self.pos -= 1;
+            return self.error("`on`");
+        }
+        self.eat_punct('|');
+        self.name()?;
+        while self.eat_punct('|') {
+            self.name()?;
+        }
+        Ok(())
+    }
+
+    fn field_args_only(&mut self) -> Result<()> {
+        self.expect_punct('(')?;
+        while !self.eat_punct(')') {
+            self.skip_description();
+            self.name()?;
+            self.expect_punct(':')?;
+            self.type_ref()?;
+            if self.eat_punct('=') {
+                self.value()?;
+            }
+            self.directives()?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum Change {
+    TypeAdded {
+        name: String,
+        kind: Kind,
+    },
+    TypeRemoved {
+        name: String,
+        kind: Kind,
+    },
+    KindChanged {
+        name: String,
+        from: Kind,
+        to: Kind,
+    },
+    InterfaceAdded {
+        ty: String,
+        interface: String,
+    },
+    InterfaceRemoved {
+        ty: String,
+        interface: String,
+    },
+    // enum value or union member
+    MemberAdded {
+        ty: String,
+        kind: Kind,
+        member: String,
+    },
+    MemberRemoved {
+        ty: String,
+        kind: Kind,
+        member: String,
+    },
+    FieldAdded {
+        ty: String,
+        field: String,
+        field_type: TypeRef,
+    },
+    FieldRemoved {
+        ty: String,
+        field: String,
+        field_type: TypeRef,
+    },
+    FieldTypeChanged {
+        ty: String,
+        field: String,
+        from: TypeRef,
+        to: TypeRef,
+    },
+    // `at` is either "Type" or "Type.field"
+    ArgumentAdded {
+        at: String,
+        arg: String,
+        arg_type: TypeRef,
+    },
+    ArgumentRemoved {
+        at: String,
+        arg: String,
+        arg_type: TypeRef,
+    },
+    ArgumentTypeChanged {
+        at: String,
+        arg: String,
+        from: TypeRef,
+        to: TypeRef,
+    },
+    DirectiveAdded {
+        at: String,
+        directive: Directive,
+    },
+    DirectiveRemoved {
+        at: String,
+        directive: Directive,
+    },
+    DirectiveChanged {
+        at: String,
+        from: Directive,
+        to: Directive,
+    },
+    SearchAdded {
+        at: String,
+        tokenizer: String,
+    },
+    SearchRemoved {
+        at: String,
+        tokenizer: String,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::TypeAdded { name, kind } => write!(f, "+ {} {}", kind, name),
+            Change::TypeRemoved { name, kind } => write!(f, "- {} {}", kind, name),
+            Change::KindChanged { name, from, to } => {
+                write!(f, "~ {}: {} -> {}", name, from, to)
+            }
+            Change::InterfaceAdded { ty, interface } => {
+                write!(f, "+ {} implements {}", ty, interface)
+            }
+            Change::InterfaceRemoved { ty, interface } => {
+                write!(f, "- {} implements {}", ty, interface)
+            }
+            Change::MemberAdded { ty, kind, member } => {
+                write!(f, "+ {}{}{}", ty, member_sep(*kind), member)
+            }
+            Change::MemberRemoved { ty, kind, member } => {
+                write!(f, "- {}{}{}", ty, member_sep(*kind), member)
+            }
+            Change::FieldAdded {
+                ty,
+                field,
+                field_type,
+            } => write!(f, "+ {}.{}: {}", ty, field, field_type),
+            Change::FieldRemoved {
+                ty,
+                field,
+                field_type,
+            } => write!(f, "- {}.{}: {}", ty, field, field_type),
+            Change::FieldTypeChanged {
+                ty,
+                field,
+                from,
+                to,
+            } => write!(f, "~ {}.{}: {} -> {}", ty, field, from, to),
+            Change::ArgumentAdded { at, arg, arg_type } => {
+                write!(f, "+ {}({}: {})", at, arg, arg_type)
+            }
+            Change::ArgumentRemoved { at, arg, arg_type } => {
+                write!(f, "- {}({}: {})", at, arg, arg_type)
+            }
+            Change::ArgumentTypeChanged { at, arg, from, to } => {
+                write!(f, "~ {}({}): {} -> {}", at, arg, from, to)
+            }
+            Change::DirectiveAdded { at, directive } => write!(f, "+ {} {}", at, directive),
+            Change::DirectiveRemoved { at, directive } => write!(f, "- {} {}", at, directive),
+            Change::DirectiveChanged { at, from, to } => {
+                write!(f, "~ {} {} -> {}", at, from, to)
+            }
+            Change::SearchAdded { at, tokenizer } => {
+                write!(f, "+ {} {}", at, search_directive(tokenizer))
+            }
+            Change::SearchRemoved { at, tokenizer } => {
+                write!(f, "- {} {}", at, search_directive(tokenizer))
+            }
+        }
+    }
+}
+
+impl Change {
+    // `breaking_reason` returns why the change is breaking, or `None` if it's
+    // safe to apply to a database that already has data.
+    pub fn breaking_reason(&self) -> Option<&'static str> {
+        match self {
+            Change::TypeRemoved { .. } => Some("type removed"),
+            Change::KindChanged { .. } => Some("kind changed"),
+            Change::InterfaceRemoved { .. } => Some("interface removed"),
+            Change::MemberRemoved { .. } => Some("member removed"),
+            Change::FieldRemoved { .. } => Some("field removed"),
+            Change::FieldAdded { field_type, .. } if is_non_null(field_type) => {
+                Some("non-null field added, existing nodes don't have it")
+            }
+            Change::FieldTypeChanged { from, to, .. } => type_change_reason(from, to),
+            Change::ArgumentRemoved { .. } => Some("argument removed"),
+            Change::ArgumentAdded { arg_type, .. } if is_non_null(arg_type) => {
+                Some("non-null argument added")
+            }
+            Change::ArgumentTypeChanged { from, to, .. } => type_change_reason(from, to),
+            Change::DirectiveAdded { directive, .. }
+            | Change::DirectiveRemoved { directive, .. }
+            | Change::DirectiveChanged { to: directive, .. } => {
+                directive_change_reason(&directive.name)
+            }
+            Change::SearchRemoved { .. } => Some("index removed"),
+            _ => None,
+        }
+    }
+}
+
+fn is_non_null(ty: &TypeRef) -> bool {
+    matches!(ty, TypeRef::NonNull(_))
+}
+
+fn type_change_reason(from: &TypeRef, to: &TypeRef) -> Option<&'static str> {
+    match (from, to) {
+        // `String!` -> `String` only relaxes the constraint
+        (TypeRef::NonNull(inner), to) if inner.as_ref() == to => None,
+        (from, TypeRef::NonNull(inner)) if inner.as_ref() == from => {
+            Some("nullable changed to non-null")
+        }
+        _ => Some("type changed"),
+    }
+}
+
+fn directive_change_reason(name: &str) -> Option<&'static str> {
+    match name {
+        "id" => Some("@id changed"),
+        // these change which predicates the data lives in
+        "dgraph" => Some("@dgraph mapping changed"),
+        "hasInverse" => Some("@hasInverse changed"),
+        _ => None,
+    }
+}
+
+// `search_directive` shows a single tokenizer as it's written in the schema.
+fn search_directive(tokenizer: &str) -> String {
+    match tokenizer {
+        "default" => String::from("@search"),
+        _ => format!("@search(by: {})", tokenizer),
+    }
+}
+
+fn member_sep(kind: Kind) -> &'static str {
+    if kind == Kind::Union {
+        " | "
+    } else {
+        "."
+    }
+}
+
+// `diff` returns a list of changes that need to be applied to `old` to get
+// `new`.
+pub fn diff(old: &Schema, new: &Schema) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for (name, old_def) in &old.types {
+        if !new.types.contains_key(name) {
+            changes.push(Change::TypeRemoved {
+                name: name.clone(),
+                kind: old_def.kind,
+            });
+        }
+    }
+    for (name, new_def) in &new.types {
+        let old_def = match old.types.get(name) {
+            Some(old_def) => old_def,
+            None => {
+                changes.push(Change::TypeAdded {
+                    name: name.clone(),
+                    kind: new_def.kind,
+                });
+                continue;
+            }
+        };
+        if old_def.kind != new_def.kind {
+            changes.push(Change::KindChanged {
+                name: name.clone(),
+                from: old_def.kind,
+                to: new_def.kind,
+            });
+        }
+        for interface in old_def.interfaces.difference(&new_def.interfaces) {
+            changes.push(Change::InterfaceRemoved {
+                ty: name.clone(),
+                interface: interface.clone(),
+            });
+        }
+        for interface in new_def.interfaces.difference(&old_def.interfaces) {
+            changes.push(Change::InterfaceAdded {
+                ty: name.clone(),
+                interface: interface.clone(),
+            });
+        }
+        for member in old_def.members.difference(&new_def.members) {
+            changes.push(Change::MemberRemoved {
+                ty: name.clone(),
+                kind: new_def.kind,
+                member: member.clone(),
+            });
+        }
+        for member in new_def.members.difference(&old_def.members) {
+            changes.push(Change::MemberAdded {
+                ty: name.clone(),
+                kind: new_def.kind,
+                member: member.clone(),
+            });
+        }
+        diff_directives(name, &old_def.directives, &new_def.directives, &mut changes);
+        diff_fields(name, &old_def.fields, &new_def.fields, &mut changes);
+    }
+    changes
+}
+
+fn diff_fields(
+    ty: &str,
+    old: &BTreeMap<String, Field>,
+    new: &BTreeMap<String, Field>,
+    changes: &mut Vec<Change>,
+) {
+    for (name, old_field) in old {
+        if !new.contains_key(name) {
+            changes.push(Change::FieldRemoved {
+                ty: ty.to_string(),
+                field: name.clone(),
+                field_type: old_field.ty.clone(),
+            });
+        }
+    }
+    for (name, new_field) in new {
+        let old_field = match old.get(name) {
+            Some(old_field) => old_field,
+            None => {
+                changes.push(Change::FieldAdded {
+                    ty: ty.to_string(),
+                    field: name.clone(),
+                    field_type: new_field.ty.clone(),
+                });
+                continue;
+            }
+        };
+        if old_field.ty != new_field.ty {
+            changes.push(Change::FieldTypeChanged {
+                ty: ty.to_string(),
+                field: name.clone(),
+                from: old_field.ty.clone(),
+                to: new_field.ty.clone(),
+            });
+        }
+        let at = format!("{}.{}", ty, name);
+        for (arg, arg_type) in &old_field.args {
+            match new_field.args.get(arg) {
+                None => changes.push(Change::ArgumentRemoved {
+                    at: at.clone(),
+                    arg: arg.clone(),
+                    arg_type: arg_type.clone(),
+                }),
+                Some(new_type) if new_type != arg_type => {
+                    changes.push(Change::ArgumentTypeChanged {
+                        at: at.clone(),
+                        arg: arg.clone(),
+                        from: arg_type.clone(),
+                        to: new_type.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        for (arg, arg_type) in &new_field.args {
+            if !old_field.args.contains_key(arg) {
+                changes.push(Change::ArgumentAdded {
+                    at: at.clone(),
+                    arg: arg.clone(),
+                    arg_type: arg_type.clone(),
+                });
+            }
+        }
+        diff_directives(&at, &old_field.directives, &new_field.directives, changes);
+    }
+}
+
+fn diff_directives(at: &str, old: &Directives, new: &Directives, changes: &mut Vec<Change>) {
+    // @search is diffed per tokenizer, whether the whole directive is added,
+    // removed or changed, so that adding an index is always safe and
+    // removing one is always breaking.
+    diff_search(at, old.get("search"), new.get("search"), changes);
+    for (name, old_dir) in old {
+        if name != "search" && !new.contains_key(name) {
+            changes.push(Change::DirectiveRemoved {
+                at: at.to_string(),
+                directive: old_dir.clone(),
+            });
+        }
+    }
+    for (name, new_dir) in new {
+        if name == "search" {
+            continue;
+        }
+        let old_dir = match old.get(name) {
+            Some(old_dir) => old_dir,
+            None => {
+                changes.push(Change::DirectiveAdded {
+                    at: at.to_string(),
+                    directive: new_dir.clone(),
+                });
+                continue;
+            }
+        };
+        if old_dir != new_dir {
+            changes.push(Change::DirectiveChanged {
+                at: at.to_string(),
+                from: old_dir.clone(),
+                to: new_dir.clone(),
+            });
+        }
+    }
+}
+
+fn diff_search(
+    at: &str,
+    old: Option<&Directive>,
+    new: Option<&Directive>,
+    changes: &mut Vec<Change>,
+) {
+    let tokenizers =
+        |dir: Option<&Directive>| dir.map(Directive::search_tokenizers).unwrap_or_default();
+    let old_tokenizers = tokenizers(old);
+    let new_tokenizers = tokenizers(new);
+    for tokenizer in old_tokenizers.difference(&new_tokenizers) {
+        changes.push(Change::SearchRemoved {
+            at: at.to_string(),
+            tokenizer: tokenizer.clone(),
+        });
+    }
+    for tokenizer in new_tokenizers.difference(&old_tokenizers) {
+        changes.push(Change::SearchAdded {
+            at: at.to_string(),
+            tokenizer: tokenizer.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `changes` diffs two schemas and shows each change along with why it's
+    // breaking, if it is.
+    fn changes(old: &str, new: &str) -> Vec<String> {
+        let old = Schema::parse(old).unwrap();
+        let new = Schema::parse(new).unwrap();
+        diff(&old, &new)
+            .iter()
+            .map(|c| match c.breaking_reason() {
+                Some(reason) => format!("{} ({})", c, reason),
+                None => c.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn block_strings_and_descriptions() {
+        let schema = Schema::parse(
+            r#"
+            """
+            a person, with "quotes" and # not a comment
+            """
+            type Person {
+                "the name"
+                name: String! # a comment
+                """
+                multi-line
+                description
+                """
+                age(
+                    "unit" unit: String = "years"
+                ): Int
+            }
+            "empty" type Empty { x: Int }
+            """""" type Blank { x: Int }
+            "#,
+        )
+        .unwrap();
+        let person = &schema.types["Person"];
+        assert_eq!(
+            person.fields.keys().collect::<Vec<_>>(),
+            vec!["age", "name"]
+        );
+        assert_eq!(person.fields["age"].args.len(), 1);
+        assert!(schema.types.contains_key("Empty"));
+        assert!(schema.types.contains_key("Blank"));
+
+        let mut lexer = Lexer::new(r#""""  a ""quoted"" \n block  """"#);
+        assert_eq!(lexer.string().unwrap(), r#"a ""quoted"" \n block"#);
+        let mut lexer = Lexer::new(r#""a \"b\"\n""#);
+        assert_eq!(lexer.string().unwrap(), "a \"b\"\n");
+    }
+
+    #[test]
+    fn unterminated_strings() {
+        let err = Schema::parse("type A {\n \"\"\"never ends\n x: Int }").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: unterminated block string");
+        let err = Schema::parse("type A {\n\n \"no end\n x: Int }").unwrap_err();
+        assert_eq!(err.to_string(), "line 3: unterminated string");
+    }
+
+    #[test]
+    fn extend_interfaces_and_unions() {
+        let schema = Schema::parse(
+            r#"
+            interface Node { id: ID! }
+            interface Named { name: String }
+            type Person implements Node & Named @key(fields: "id") {
+                id: ID!
+                name: String
+            }
+            extend type Person implements Aged @cacheControl(maxAge: 10) {
+                age: Int
+            }
+            type Robot implements & Node { id: ID! }
+            union Thing = | Person | Robot
+            extend union Thing = Car
+            enum Color { RED @deprecated GREEN }
+            scalar DateTime
+            schema { query: Query }
+            directive @custom(http: String) repeatable on FIELD_DEFINITION | OBJECT
+            "#,
+        )
+        .unwrap();
+        let person = &schema.types["Person"];
+        assert_eq!(
+            person.interfaces.iter().collect::<Vec<_>>(),
+            vec!["Aged", "Named", "Node"]
+        );
+        assert_eq!(
+            person.fields.keys().collect::<Vec<_>>(),
+            vec!["age", "id", "name"]
+        );
+        assert!(person.directives.contains_key("key"));
+        assert!(person.directives.contains_key("cacheControl"));
+        assert_eq!(
+            schema.types["Robot"].interfaces.iter().collect::<Vec<_>>(),
+            vec!["Node"]
+        );
+        let thing = &schema.types["Thing"];
+        assert_eq!(thing.kind, Kind::Union);
+        assert_eq!(
+            thing.members.iter().collect::<Vec<_>>(),
+            vec!["Car", "Person", "Robot"]
+        );
+        assert_eq!(
+            schema.types["Color"].members.iter().collect::<Vec<_>>(),
+            vec!["GREEN", "RED"]
+        );
+        assert_eq!(schema.types["DateTime"].kind, Kind::Scalar);
+        assert!(!schema.types.contains_key("Query"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = Schema::parse("type A {\n  x Int\n}").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: expected `:`, found Name(\"Int\")");
+        let err = Schema::parse("query { x }").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1: expected a type system definition, found Name(\"query\")"
+        );
+        let err = Schema::parse("type A { x: [Int }").unwrap_err();
+        assert_eq!(err.to_string(), "line 1: expected `]`, found Punct('}')");
+    }
+
+    #[test]
+    fn interface_and_member_changes() {
+        assert_eq!(
+            changes(
+                "type A implements B { x: Int } union U = A | C enum E { X Y }",
+                "type A implements D { x: Int } union U = A | F enum E { X Z }",
+            ),
+            vec![
+                "- A implements B (interface removed)",
+                "+ A implements D",
+                "- E.Y (member removed)",
+                "+ E.Z",
+                "- U | C (member removed)",
+                "+ U | F",
+            ]
+        );
+        assert_eq!(
+            changes("type A { x: Int }", "interface A { x: Int }"),
+            vec!["~ A: type -> interface (kind changed)"]
+        );
+    }
+
+    #[test]
+    fn field_type_changes() {
+        assert_eq!(
+            changes(
+                "type A { a: String! b: String c: [Int] d: Int e: [Int!]! f: Int }",
+                "type A { a: String b: String! c: Int d: [Int] e: [Int]! g: Int! h: Int }",
+            ),
+            vec![
+                "- A.f: Int (field removed)",
+                "~ A.a: String! -> String",
+                "~ A.b: String -> String! (nullable changed to non-null)",
+                "~ A.c: [Int] -> Int (type changed)",
+                "~ A.d: Int -> [Int] (type changed)",
+                "~ A.e: [Int!]! -> [Int]! (type changed)",
+                "+ A.g: Int! (non-null field added, existing nodes don't have it)",
+                "+ A.h: Int",
+            ]
+        );
+    }
+
+    #[test]
+    fn argument_changes() {
+        assert_eq!(
+            changes(
+                "type A { f(a: Int!, b: Int, c: Int): Int }",
+                "type A { f(a: Int, b: Int!, d: Int!, e: Int): Int }",
+            ),
+            vec![
+                "~ A.f(a): Int! -> Int",
+                "~ A.f(b): Int -> Int! (nullable changed to non-null)",
+                "- A.f(c: Int) (argument removed)",
+                "+ A.f(d: Int!) (non-null argument added)",
+                "+ A.f(e: Int)",
+            ]
+        );
+    }
+
+    #[test]
+    fn search_tokenizers() {
+        // adding an index is safe, whether the field had one or not
+        assert_eq!(
+            changes(
+                "type A { a: String @search(by: [hash]) b: String c: String d: Int }",
+                "type A { a: String @search(by: [hash, term]) b: String @search(by: [exact, term]) c: String @search(by: hash) d: Int @search }",
+            ),
+            vec![
+                "+ A.a @search(by: term)",
+                "+ A.b @search(by: exact)",
+                "+ A.b @search(by: term)",
+                "+ A.c @search(by: hash)",
+                "+ A.d @search",
+            ]
+        );
+        // removing one is breaking, whether the whole @search goes or not
+        assert_eq!(
+            changes(
+                "type A { a: String @search(by: [hash, term]) b: String @search(by: [exact]) c: Int @search }",
+                "type A { a: String @search(by: [term]) b: String c: Int @search(by: [int]) }",
+            ),
+            vec![
+                "- A.a @search(by: hash) (index removed)",
+                "- A.b @search(by: exact) (index removed)",
+                "- A.c @search (index removed)",
+                "+ A.c @search(by: int)",
+            ]
+        );
+        assert!(changes(
+            "type A { a: String @search(by: [hash, term]) }",
+            "type A { a: String @search(by: [term, hash]) }",
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn directive_changes() {
+        assert_eq!(
+            changes(
+                r#"type A @auth(query: { rule: "x" }) { a: String! @id b: String c: [A] @hasInverse(field: d) }"#,
+                r#"type A @auth(query: { rule: "y" }) { a: String! b: String @id c: [A] @hasInverse(field: e) }"#,
+            ),
+            vec![
+                r#"~ A @auth(query: { rule: "x" }) -> @auth(query: { rule: "y" })"#,
+                "- A.a @id (@id changed)",
+                "+ A.b @id (@id changed)",
+                "~ A.c @hasInverse(field: d) -> @hasInverse(field: e) (@hasInverse changed)",
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_order_whitespace_and_comments() {
+        assert!(changes(
+            "type A { a: Int b: String @search(by: [hash]) }\nenum E { X Y }",
+            "# comment\nenum E {\n  Y, X\n}\ntype A {\n  b: String @search(by: hash)\n  a: Int\n}",
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,50 @@
+// secrets given on the command line end up in shell history and are visible
+// to anyone who can run `ps`, so they can be read from files (for example
+// docker or kubernetes secrets) or environment variables as well.
+
+use anyhow::{anyhow, Context, Result};
+use std::{env, fs};
+
+// setting this variable silences warnings about secrets on the command line.
+const ALLOW_ARGV_SECRETS: &str = "DGRAPH_ADMIN_INSECURE_ALLOW_ARGV_SECRETS";
+
+// `resolve` returns a secret given either directly with `flag`, or as a path
+// to a file with `flag`-file. it's an error to give both.
+pub fn resolve(flag: &str, value: Option<String>, file: Option<String>) -> Result<Option<String>> {
+    match (value, file) {
+        (Some(_), Some(_)) => Err(anyhow!(
+            "{} and {}-file can not be used together",
+            flag,
+            flag
+        )),
+        (Some(value), None) => {
+            warn_argv(flag);
+            Ok(Some(value))
+        }
+        (None, Some(path)) => read_file(&path).map(Some),
+        (None, None) => Ok(None),
+    }
+}
+
+// `from_env` returns the value of a non-empty environment variable.
+pub fn from_env(var: &str) -> Option<String> {
+    env::var(var).ok().filter(|v| !v.is_empty())
+}
+
+// `read_file` reads a secret from a file.
+pub fn read_file(path: &str) -> Result<String> {
+    let secret =
+        fs::read_to_string(path).with_context(|| format!("could not read secret from {}", path))?;
+    // files usually end with a newline, which is not a part of the secret
+    Ok(secret.trim_end_matches(&['\r', '\n'][..]).to_string())
+}
+
+fn warn_argv(flag: &str) {
+    if from_env(ALLOW_ARGV_SECRETS).is_some() {
+        return;
+    }
+    eprintln!(
+        "warning: {} was given on the command line, where it's visible in process list and shell history. use {}-file or an environment variable instead, or set {}=1 to silence this warning.",
+        flag, flag, ALLOW_ARGV_SECRETS
+    );
+}
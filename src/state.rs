@@ -0,0 +1,534 @@
+// membership state of the cluster: groups, their members and tablets, as
+// zero sees it. alphas expose it through the admin `state` query, zero
+// through `/state` on its http port.
+// see: https://dgraph.io/docs/deploy/dgraph-zero/#more-about-the-state-endpoint
+
+use crate::{
+    dgraph::Dgraph,
+    output::{self, Format, Report},
+    parse_duration,
+    table::format_table,
+    watch::{self, highlight, Change, Snapshot},
+    zero::Zero,
+};
+use anyhow::{anyhow, Result};
+use argh::FromArgs;
+use humantime::format_duration;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "cluster-state",
+    description = "show groups, their members and tablets"
+)]
+pub struct ClusterState {
+    #[argh(
+        option,
+        description = "zero's http url (like localhost:6080) to get the state from, instead of alpha's admin endpoint"
+    )]
+    zero_url: Option<String>,
+
+    #[argh(
+        switch,
+        description = "poll until interrupted, highlighting members and tablets that changed"
+    )]
+    watch: bool,
+
+    #[argh(
+        option,
+        from_str_fn(parse_duration),
+        default = "Duration::from_secs(2)",
+        description = "how often to poll with --watch (default: 2s)"
+    )]
+    interval: Duration,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Member {
+    #[serde(default, deserialize_with = "uint")]
+    pub id: u64,
+    #[serde(default, deserialize_with = "uint")]
+    pub group_id: u64,
+    #[serde(default)]
+    pub addr: String,
+    #[serde(default)]
+    pub leader: bool,
+    #[serde(default)]
+    pub am_dead: bool,
+    // unix time, 0 if unknown
+    #[serde(default, deserialize_with = "uint")]
+    pub last_update: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Tablet {
+    pub predicate: String,
+    // taken out of the predicate, see `split_namespace`
+    #[serde(default, skip_deserializing)]
+    pub namespace: u64,
+    #[serde(default, deserialize_with = "uint")]
+    pub group_id: u64,
+    #[serde(default, deserialize_with = "uint")]
+    pub on_disk_bytes: u64,
+    #[serde(default, deserialize_with = "uint")]
+    pub uncompressed_bytes: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Group {
+    // zero's `/state` has groups keyed by id instead
+    #[serde(default, deserialize_with = "uint")]
+    pub id: u64,
+    #[serde(default, deserialize_with = "list_or_map")]
+    pub members: Vec<Member>,
+    #[serde(default, deserialize_with = "list_or_map")]
+    pub tablets: Vec<Tablet>,
+}
+
+impl Tablet {
+    // `key` identifies the tablet across namespaces, the same way newer
+    // versions of dgraph print it.
+    pub fn key(&self) -> String {
+        format!("{}-{}", self.namespace, &self.predicate)
+    }
+}
+
+impl Group {
+    pub fn on_disk_bytes(&self) -> u64 {
+        self.tablets.iter().map(|t| t.on_disk_bytes).sum()
+    }
+}
+
+// field names are the same as in `state` query and zero's `/state`.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct State {
+    #[serde(default, deserialize_with = "groups")]
+    pub groups: Vec<Group>,
+    #[serde(default, deserialize_with = "list_or_map")]
+    pub zeros: Vec<Member>,
+    // leases: the next uid, timestamp, namespace and raft id to hand out
+    // are above these
+    #[serde(rename = "maxUID", default, deserialize_with = "uint")]
+    pub max_uid: u64,
+    #[serde(default, deserialize_with = "uint")]
+    pub max_txn_ts: u64,
+    #[serde(rename = "maxNsID", default, deserialize_with = "uint")]
+    pub max_ns_id: u64,
+    #[serde(default, deserialize_with = "uint")]
+    pub max_raft_id: u64,
+    // nodes removed from the cluster, their ids can not be reused
+    #[serde(default, deserialize_with = "list_or_map")]
+    pub removed: Vec<Member>,
+    pub license: Option<License>,
+}
+
+// enterprise license, or the trial one that comes with a new cluster.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct License {
+    #[serde(default)]
+    pub user: String,
+    #[serde(default, deserialize_with = "uint")]
+    pub max_nodes: u64,
+    // unix time
+    #[serde(default, deserialize_with = "uint")]
+    pub expiry_ts: u64,
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+// graphql has no maps, so the `state` query returns lists, while zero's
+// `/state` has maps keyed by id (or by predicate, for tablets).
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ListOrMap<T> {
+    List(Vec<T>),
+    Map(BTreeMap<String, T>),
+}
+
+fn list_or_map<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(match Option::<ListOrMap<T>>::deserialize(deserializer)? {
+        Some(ListOrMap::List(items)) => items,
+        Some(ListOrMap::Map(items)) => items.into_values().collect(),
+        None => Vec::new(),
+    })
+}
+
+// `groups` is `list_or_map`, but takes group ids from the keys.
+fn groups<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Group>, D::Error> {
+    Ok(
+        match Option::<ListOrMap<Group>>::deserialize(deserializer)? {
+            Some(ListOrMap::List(groups)) => groups,
+            Some(ListOrMap::Map(groups)) => groups
+                .into_iter()
+                .map(|(id, mut group)| {
+                    group.id = id.parse().unwrap_or(group.id);
+                    group
+                })
+                .collect(),
+            None => Vec::new(),
+        },
+    )
+}
+
+// `uint` accepts both numbers and strings, because protobuf's json (which
+// zero uses) has 64 bit integers as strings.
+pub fn uint<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match JsonValue::deserialize(deserializer)? {
+        JsonValue::Null => Ok(0),
+        JsonValue::Number(n) => n
+            .as_u64()
+            .ok_or_else(|| D::Error::custom(format!("invalid unsigned integer {}", n))),
+        JsonValue::String(s) => s
+            .parse()
+            .map_err(|_| D::Error::custom(format!("invalid unsigned integer {:?}", s))),
+        value => Err(D::Error::custom(format!(
+            "expected unsigned integer, got {}",
+            value
+        ))),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct StateData {
+    state: State,
+}
+
+// `get_state` gets the state from zero if `zero_url` is given, otherwise
+// from the alpha.
+pub fn get_state(dgraph: &Dgraph, zero_url: Option<&str>) -> Result<State> {
+    let mut state = match zero_url {
+        Some(url) => Zero::new(dgraph, url)?.state()?,
+        None => {
+            dgraph
+                .query::<_, StateData>(
+                    "admin",
+                    r#"query state {
+                        state {
+                            groups {
+                                id
+                                members { id groupId addr leader amDead lastUpdate }
+                                tablets { predicate groupId onDiskBytes uncompressedBytes }
+                            }
+                            zeros { id groupId addr leader amDead lastUpdate }
+                            maxUID maxTxnTs maxNsID maxRaftId
+                            removed { id groupId addr leader amDead lastUpdate }
+                            license { user maxNodes expiryTs enabled }
+                        }
+                    }"#,
+                    (),
+                )?
+                .ok_or_else(|| anyhow!("empty response"))?
+                .state
+        }
+    };
+    state.groups.sort_by_key(|g| g.id);
+    for group in &mut state.groups {
+        group.members.sort_by_key(|m| m.id);
+        for tablet in &mut group.tablets {
+            if let Some((namespace, predicate)) = split_namespace(&tablet.predicate) {
+                tablet.namespace = namespace;
+                tablet.predicate = predicate.to_string();
+            }
+        }
+        // biggest first, they are what makes groups unbalanced
+        group.tablets.sort_by(|a, b| {
+            b.on_disk_bytes
+                .cmp(&a.on_disk_bytes)
+                .then_with(|| a.predicate.cmp(&b.predicate))
+        });
+    }
+    state.zeros.sort_by_key(|m| m.id);
+    state.removed.sort_by_key(|m| m.id);
+    Ok(state)
+}
+
+impl State {
+    // `lines` renders the state, along with the key of the member or tablet
+    // that each line is about, for highlighting.
+    fn lines(&self) -> Vec<(Option<String>, String)> {
+        let mut lines = Vec::new();
+        lines.push((None, String::from("zeros:")));
+        push_table(&mut lines, MEMBER_HEADERS, member_rows(&self.zeros));
+        for group in &self.groups {
+            lines.push((None, String::new()));
+            lines.push((
+                None,
+                format!(
+                    "group {}: {} tablet(s), {} on disk",
+                    group.id,
+                    group.tablets.len(),
+                    format_bytes(group.on_disk_bytes())
+                ),
+            ));
+            push_table(&mut lines, MEMBER_HEADERS, member_rows(&group.members));
+            if !group.tablets.is_empty() {
+                lines.push((None, String::new()));
+                let rows = group
+                    .tablets
+                    .iter()
+                    .map(|t| {
+                        (
+                            Some(t.key()),
+                            vec![
+                                t.predicate.clone(),
+                                t.namespace.to_string(),
+                                format_bytes(t.on_disk_bytes),
+                                format_bytes(t.uncompressed_bytes),
+                            ],
+                        )
+                    })
+                    .collect();
+                push_table(
+                    &mut lines,
+                    &["PREDICATE", "NAMESPACE", "ON DISK", "UNCOMPRESSED"],
+                    rows,
+                );
+            }
+        }
+        lines.push((None, String::new()));
+        lines.push((
+            None,
+            format!(
+                "max leases: uid {}, txn ts {}, namespace {}, raft id {}",
+                self.max_uid, self.max_txn_ts, self.max_ns_id, self.max_raft_id
+            ),
+        ));
+        if !self.removed.is_empty() {
+            lines.push((None, String::new()));
+            lines.push((None, String::from("removed:")));
+            push_table(&mut lines, MEMBER_HEADERS, member_rows(&self.removed));
+        }
+        lines
+    }
+}
+
+// `push_table` adds an indented table, rows are paired with their keys.
+fn push_table(
+    lines: &mut Vec<(Option<String>, String)>,
+    headers: &[&str],
+    keyed_rows: Vec<(Option<String>, Vec<String>)>,
+) {
+    let (keys, rows): (Vec<_>, Vec<_>) = keyed_rows.into_iter().unzip();
+    let table = format_table(headers, &rows);
+    lines.push((None, format!("  {}", &table[0])));
+    for (key, line) in keys.into_iter().zip(&table[1..]) {
+        lines.push((key, format!("  {}", line)));
+    }
+}
+
+const MEMBER_HEADERS: &[&str] = &["ID", "GROUP", "ADDRESS", "LEADER", "DEAD", "LAST UPDATE"];
+
+fn member_rows(members: &[Member]) -> Vec<(Option<String>, Vec<String>)> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    members
+        .iter()
+        .map(|m| {
+            (
+                Some(m.addr.clone()),
+                vec![
+                    m.id.to_string(),
+                    m.group_id.to_string(),
+                    m.addr.clone(),
+                    String::from(if m.leader { "yes" } else { "" }),
+                    String::from(if m.am_dead { "yes" } else { "" }),
+                    if m.last_update > 0 {
+                        format!(
+                            "{} ago",
+                            format_duration(Duration::from_secs(now.saturating_sub(m.last_update)))
+                        )
+                    } else {
+                        String::from("-")
+                    },
+                ],
+            )
+        })
+        .collect()
+}
+
+// `split_namespace` splits off the namespace. since v21.03 predicates are
+// prefixed with 8 bytes of it (big endian), newer versions print it as
+// `<namespace>-<predicate>` instead.
+fn split_namespace(predicate: &str) -> Option<(u64, &str)> {
+    let bytes = predicate.as_bytes();
+    if bytes.len() > 8 && bytes[0] == 0 && bytes[..8].iter().all(u8::is_ascii) {
+        let namespace = bytes[..8]
+            .iter()
+            .fold(0u64, |ns, b| (ns << 8) | u64::from(*b));
+        return Some((namespace, &predicate[8..]));
+    }
+    let (namespace, rest) = predicate.split_once('-')?;
+    if namespace.is_empty() || !namespace.bytes().all(|b| b.is_ascii_digit()) || rest.is_empty() {
+        return None;
+    }
+    Some((namespace.parse().ok()?, rest))
+}
+
+// `format_bytes` formats the size with binary units, like `1.5 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+impl Report for State {
+    fn print_text(&self) {
+        for (_, line) in self.lines() {
+            println!("{}", line);
+        }
+    }
+}
+
+impl Snapshot for State {
+    fn changes(&self, previous: &Self) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let mut change = |key: &str, description: String| {
+            changes.push(Change {
+                key: key.to_string(),
+                description,
+            })
+        };
+
+        let members = |state: &State| -> HashMap<String, (u64, bool, bool)> {
+            state
+                .zeros
+                .iter()
+                .chain(state.groups.iter().flat_map(|g| &g.members))
+                .map(|m| (m.addr.clone(), (m.group_id, m.leader, m.am_dead)))
+                .collect()
+        };
+        let (before, now) = (members(previous), members(self));
+        for (addr, &(group, leader, dead)) in &now {
+            match before.get(addr) {
+                None => change(addr, format!("joined group {}", group)),
+                Some(&(prev_group, prev_leader, prev_dead)) => {
+                    if group != prev_group {
+                        change(
+                            addr,
+                            format!("moved from group {} to {}", prev_group, group),
+                        );
+                    }
+                    if leader && !prev_leader {
+                        change(addr, format!("became leader of group {}", group));
+                    }
+                    if dead != prev_dead {
+                        let state = if dead { "dead" } else { "alive" };
+                        change(addr, format!("is {}", state));
+                    }
+                }
+            }
+        }
+        for addr in before.keys().filter(|addr| !now.contains_key(*addr)) {
+            change(addr, String::from("left"));
+        }
+
+        let tablets = |state: &State| -> HashMap<String, u64> {
+            state
+                .groups
+                .iter()
+                .flat_map(|g| &g.tablets)
+                .map(|t| (t.key(), t.group_id))
+                .collect()
+        };
+        let (before, now) = (tablets(previous), tablets(self));
+        for (predicate, group) in &now {
+            match before.get(predicate) {
+                None => change(predicate, format!("served by group {}", group)),
+                Some(prev) if prev != group => {
+                    change(predicate, format!("moved from group {} to {}", prev, group))
+                }
+                _ => {}
+            }
+        }
+        changes
+    }
+
+    fn print_highlighted(&self, highlighted: &HashSet<String>) {
+        for (key, line) in self.lines() {
+            match key {
+                Some(key) if highlighted.contains(&key) => println!("{}", highlight(&line)),
+                _ => println!("{}", line),
+            }
+        }
+    }
+}
+
+impl ClusterState {
+    pub fn run(self, dgraph: &Dgraph, format: Format) -> Result<i32> {
+        let zero_url = self.zero_url.as_deref();
+        if !self.watch {
+            return output::print(format, &get_state(dgraph, zero_url)?);
+        }
+        if format != Format::Text {
+            return Err(anyhow!("--watch works only with text output"));
+        }
+        watch::watch("cluster-state", self.interval, || {
+            get_state(dgraph, zero_url)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_binary_namespace() {
+        assert_eq!(split_namespace("\0\0\0\0\0\0\0\0email"), Some((0, "email")));
+        assert_eq!(
+            split_namespace("\0\0\0\0\0\0\0\x02dgraph.type"),
+            Some((2, "dgraph.type"))
+        );
+        assert_eq!(
+            split_namespace("\0\0\0\0\0\0\x01\0name"),
+            Some((256, "name"))
+        );
+        // nothing after the namespace
+        assert_eq!(split_namespace("\0\0\0\0\0\0\0\0"), None);
+    }
+
+    #[test]
+    fn splits_printed_namespace() {
+        assert_eq!(split_namespace("0-email"), Some((0, "email")));
+        assert_eq!(split_namespace("2-dgraph.type"), Some((2, "dgraph.type")));
+        assert_eq!(split_namespace("10-first-name"), Some((10, "first-name")));
+        assert_eq!(split_namespace("0-"), None);
+        assert_eq!(split_namespace("-email"), None);
+        assert_eq!(split_namespace("x1-email"), None);
+        assert_eq!(split_namespace("+1-email"), None);
+        // doesn't fit in u64
+        assert_eq!(split_namespace("18446744073709551616-email"), None);
+    }
+
+    #[test]
+    fn leaves_plain_predicates() {
+        // versions before v21.03 have no namespaces
+        assert_eq!(split_namespace("email"), None);
+        assert_eq!(split_namespace("first-name"), None);
+        assert_eq!(split_namespace("dgraph.type"), None);
+    }
+}
@@ -0,0 +1,28 @@
+// `print_table` prints rows as left aligned columns separated by two spaces.
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    for line in format_table(headers, rows) {
+        println!("{}", line);
+    }
+}
+
+// `format_table` returns lines of the table, the first one is headers.
+pub fn format_table(headers: &[&str], rows: &[Vec<String>]) -> Vec<String> {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+    let headers: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    std::iter::once(&headers)
+        .chain(rows)
+        .map(|row| {
+            let line: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| format!("{:width$}", cell, width = widths[i]))
+                .collect();
+            line.join("  ").trim_end().to_string()
+        })
+        .collect()
+}
@@ -0,0 +1,466 @@
+// moving tablets (all data of a predicate) between groups. moving a tablet
+// blocks writes to the predicate until the move is done.
+// see: https://dgraph.io/docs/deploy/dgraph-zero/#moving-tablets
+
+use crate::{
+    confirm,
+    dgraph::{Dgraph, Response},
+    output::Report,
+    state::{format_bytes, get_state, State},
+    table::print_table,
+};
+use anyhow::{anyhow, Context, Result};
+use argh::FromArgs;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+// groups closer in size than this (relative to the average group) are
+// balanced, moving tablets any further is not worth blocking writes.
+const TOLERANCE: f64 = 0.1;
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "move-tablet",
+    description = "move a predicate to another group"
+)]
+pub struct MoveTablet {
+    #[argh(positional)]
+    predicate: String,
+
+    #[argh(option, description = "group to move the predicate to")]
+    group: u64,
+
+    #[argh(
+        option,
+        default = "0",
+        description = "namespace of the predicate (default: 0)"
+    )]
+    namespace: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct MoveTabletPayload {
+    response: Response,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct MoveTabletData {
+    move_tablet: MoveTabletPayload,
+}
+
+#[derive(Serialize)]
+pub struct TabletMoved {
+    message: String,
+}
+
+impl Report for TabletMoved {
+    fn print_text(&self) {
+        println!("{}", &self.message);
+    }
+}
+
+impl MoveTablet {
+    pub fn exec(self, dgraph: &Dgraph) -> Result<TabletMoved> {
+        Ok(TabletMoved {
+            message: move_tablet(dgraph, &self.predicate, self.namespace, self.group)?,
+        })
+    }
+}
+
+// `move_tablet` waits for the move to finish, and returns zero's message.
+fn move_tablet(dgraph: &Dgraph, predicate: &str, namespace: u64, group: u64) -> Result<String> {
+    let mut input = json!({ "tablet": predicate, "groupId": group });
+    // versions before v21.03 don't know about namespaces
+    if namespace != 0 {
+        input["namespace"] = json!(namespace);
+    }
+    let resp = dgraph.query::<_, MoveTabletData>(
+        "admin",
+        r#"mutation moveTablet($input: MoveTabletInput!) {
+            moveTablet(input: $input) {
+                response { message }
+            }
+        }"#,
+        json!({ "input": input }),
+    )?;
+    Ok(resp
+        .ok_or_else(|| anyhow!("empty response"))?
+        .move_tablet
+        .response
+        .message)
+}
+
+#[derive(FromArgs)]
+#[argh(
+    subcommand,
+    name = "rebalance",
+    description = "plan moving tablets to even out sizes of groups, and apply the plan"
+)]
+pub struct Rebalance {
+    #[argh(switch, description = "only print the plan")]
+    plan: bool,
+
+    #[argh(switch, description = "move the tablets")]
+    apply: bool,
+
+    #[argh(switch, description = "do not ask for confirmation")]
+    yes: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Move {
+    predicate: String,
+    namespace: u64,
+    from: u64,
+    to: u64,
+    on_disk_bytes: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupSize {
+    id: u64,
+    // on disk bytes, before and after the moves
+    before: u64,
+    after: u64,
+}
+
+#[derive(Serialize)]
+pub struct RebalancePlan {
+    moves: Vec<Move>,
+    groups: Vec<GroupSize>,
+    applied: bool,
+}
+
+impl Report for RebalancePlan {
+    fn print_text(&self) {
+        if self.moves.is_empty() {
+            println!("nothing to move, moving tablets would not make groups more even");
+            return;
+        }
+        for m in &self.moves {
+            println!(
+                "{} {} ({}, namespace {}) from group {} to group {}",
+                if self.applied { "moved" } else { "move" },
+                &m.predicate,
+                format_bytes(m.on_disk_bytes),
+                m.namespace,
+                m.from,
+                m.to
+            );
+        }
+        println!();
+        let rows: Vec<Vec<String>> = self
+            .groups
+            .iter()
+            .map(|g| {
+                vec![
+                    g.id.to_string(),
+                    format_bytes(g.before),
+                    format_bytes(g.after),
+                ]
+            })
+            .collect();
+        print_table(&["GROUP", "BEFORE", "AFTER"], &rows);
+        if !self.applied {
+            println!();
+            println!("use --apply to move the tablets");
+        }
+    }
+}
+
+impl Rebalance {
+    pub fn exec(self, dgraph: &Dgraph) -> Result<RebalancePlan> {
+        if self.plan == self.apply {
+            return Err(anyhow!(
+                "use either --plan to print the plan, or --apply to move the tablets"
+            ));
+        }
+        let mut plan = plan(&get_state(dgraph, None)?);
+        if self.plan || plan.moves.is_empty() {
+            return Ok(plan);
+        }
+        confirm(
+            dgraph,
+            &match plan.moves.len() {
+                1 => String::from("move 1 tablet, blocking writes to its predicate while it moves"),
+                n => format!(
+                    "move {} tablets, blocking writes to their predicates while they move",
+                    n
+                ),
+            },
+            self.yes,
+        )?;
+        dgraph.for_each_request(&plan.moves, |m| {
+            eprintln!(
+                "moving {} ({}) from group {} to group {}",
+                &m.predicate,
+                format_bytes(m.on_disk_bytes),
+                m.from,
+                m.to
+            );
+            let message = move_tablet(dgraph, &m.predicate, m.namespace, m.to)
+                .with_context(|| format!("could not move {}", &m.predicate))?;
+            eprintln!("{}", message);
+            Ok(())
+        })?;
+        plan.applied = true;
+        Ok(plan)
+    }
+}
+
+// `plan` moves tablets from the biggest group to the smallest one, picking
+// the tablet that brings the two closest in size, for as long as it makes a
+// difference. every move makes the sizes more even, so it comes to an end.
+fn plan(state: &State) -> RebalancePlan {
+    // groups without members are being removed, tablets can't go there
+    let before: Vec<(u64, u64)> = state
+        .groups
+        .iter()
+        .filter(|g| !g.members.is_empty())
+        .map(|g| (g.id, g.on_disk_bytes()))
+        .collect();
+    let mut sizes: BTreeMap<u64, u64> = before.iter().cloned().collect();
+    // tablets that could be moved, zero refuses to move reserved predicates
+    let mut tablets: Vec<Move> = state
+        .groups
+        .iter()
+        .flat_map(|g| &g.tablets)
+        .filter(|t| !t.predicate.starts_with("dgraph.") && t.on_disk_bytes > 0)
+        .map(|t| Move {
+            predicate: t.predicate.clone(),
+            namespace: t.namespace,
+            from: t.group_id,
+            to: t.group_id,
+            on_disk_bytes: t.on_disk_bytes,
+        })
+        .collect();
+    // smallest first, moving less data is quicker when it's a tie
+    tablets.sort_by_key(|t| t.on_disk_bytes);
+
+    let average = sizes.values().sum::<u64>() as f64 / sizes.len().max(1) as f64;
+    loop {
+        let big = sizes.iter().max_by_key(|(_, size)| **size);
+        let small = sizes.iter().min_by_key(|(_, size)| **size);
+        let (big, small, gap) = match (big, small) {
+            (Some((&big, big_size)), Some((&small, small_size))) => {
+                (big, small, big_size - small_size)
+            }
+            _ => break,
+        };
+        if gap as f64 <= average * TOLERANCE {
+            break;
+        }
+        // the gap between the two after moving the tablet
+        let gap_after = |t: &Move| (gap as i128 - 2 * t.on_disk_bytes as i128).unsigned_abs();
+        let tablet = match tablets
+            .iter_mut()
+            .filter(|t| t.to == big)
+            .min_by_key(|t| gap_after(t))
+        {
+            Some(tablet)
+                if gap.saturating_sub(gap_after(tablet) as u64) as f64 > average * TOLERANCE =>
+            {
+                tablet
+            }
+            _ => break,
+        };
+        tablet.to = small;
+        *sizes.get_mut(&big).unwrap() -= tablet.on_disk_bytes;
+        *sizes.get_mut(&small).unwrap() += tablet.on_disk_bytes;
+    }
+    RebalancePlan {
+        // a tablet may have been moved more than once, or back
+        moves: tablets.into_iter().filter(|t| t.from != t.to).collect(),
+        groups: before
+            .into_iter()
+            .map(|(id, before)| GroupSize {
+                id,
+                before,
+                after: sizes[&id],
+            })
+            .collect(),
+        applied: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{Group, Member, Tablet};
+
+    // `state` makes a cluster state out of groups of `(predicate, size)`,
+    // a group with no tablets is empty, `None` has no members.
+    fn state(groups: &[Option<&[(&str, u64)]>]) -> State {
+        let groups = groups
+            .iter()
+            .enumerate()
+            .map(|(i, tablets)| {
+                let id = i as u64 + 1;
+                Group {
+                    id,
+                    members: match tablets {
+                        Some(_) => vec![Member {
+                            id,
+                            group_id: id,
+                            addr: format!("alpha{}:7080", id),
+                            leader: true,
+                            am_dead: false,
+                            last_update: 0,
+                        }],
+                        None => Vec::new(),
+                    },
+                    tablets: tablets
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|(predicate, size)| Tablet {
+                            predicate: predicate.to_string(),
+                            namespace: 0,
+                            group_id: id,
+                            on_disk_bytes: *size,
+                            uncompressed_bytes: *size,
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        State {
+            groups,
+            zeros: Vec::new(),
+            max_uid: 0,
+            max_txn_ts: 0,
+            max_ns_id: 0,
+            max_raft_id: 0,
+            removed: Vec::new(),
+            license: None,
+        }
+    }
+
+    fn moves(rebalanced: &RebalancePlan) -> Vec<(&str, u64, u64)> {
+        rebalanced
+            .moves
+            .iter()
+            .map(|m| (m.predicate.as_str(), m.from, m.to))
+            .collect()
+    }
+
+    fn sizes(rebalanced: &RebalancePlan) -> Vec<u64> {
+        rebalanced.groups.iter().map(|g| g.after).collect()
+    }
+
+    #[test]
+    fn balanced_groups_stay() {
+        let rebalanced = plan(&state(&[
+            Some(&[("a", 100), ("b", 5)]),
+            Some(&[("c", 100)]),
+        ]));
+        assert!(rebalanced.moves.is_empty());
+        assert_eq!(sizes(&rebalanced), vec![105, 100]);
+        let rebalanced = plan(&state(&[]));
+        assert!(rebalanced.moves.is_empty());
+        assert!(rebalanced.groups.is_empty());
+    }
+
+    #[test]
+    fn evens_out_groups() {
+        let rebalanced = plan(&state(&[
+            Some(&[("a", 500), ("b", 400), ("c", 300), ("d", 200), ("e", 100)]),
+            Some(&[]),
+            Some(&[]),
+        ]));
+        assert_eq!(
+            moves(&rebalanced),
+            vec![("e", 1, 3), ("b", 1, 3), ("a", 1, 2)]
+        );
+        assert_eq!(sizes(&rebalanced), vec![500, 500, 500]);
+        assert_eq!(rebalanced.groups[0].before, 1500);
+    }
+
+    #[test]
+    fn does_not_move_what_does_not_help() {
+        // moving `a` would only swap the sizes of groups
+        let rebalanced = plan(&state(&[Some(&[("a", 1000)]), Some(&[("b", 10)])]));
+        assert!(rebalanced.moves.is_empty());
+        // reserved predicates and empty tablets stay where they are
+        let rebalanced = plan(&state(&[
+            Some(&[("dgraph.type", 1000), ("a", 0)]),
+            Some(&[]),
+        ]));
+        assert!(rebalanced.moves.is_empty());
+    }
+
+    #[test]
+    fn skips_groups_without_members() {
+        let rebalanced = plan(&state(&[Some(&[("a", 100), ("b", 100)]), None]));
+        assert!(rebalanced.moves.is_empty());
+        let rebalanced = plan(&state(&[Some(&[("a", 100), ("b", 100)]), None, Some(&[])]));
+        assert_eq!(moves(&rebalanced), vec![("a", 1, 3)]);
+        assert_eq!(
+            rebalanced.groups.iter().map(|g| g.id).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+    }
+
+    #[test]
+    fn terminates_within_tolerance() {
+        // a tiny linear congruential generator, for sizes that are all over
+        // the place but the same on every run
+        let mut seed: u64 = 42;
+        let mut next = move |max: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % max
+        };
+        for _ in 0..200 {
+            let names: Vec<String> = (0..40).map(|i| format!("p{}", i)).collect();
+            let mut groups: Vec<Vec<(&str, u64)>> = vec![Vec::new(); next(5) as usize + 1];
+            for name in &names[..next(40) as usize] {
+                let size = match next(3) {
+                    0 => next(100),
+                    1 => next(10_000),
+                    _ => next(1_000_000),
+                };
+                let group = next(groups.len() as u64) as usize;
+                groups[group].push((name, size));
+            }
+            let groups: Vec<Option<&[(&str, u64)]>> =
+                groups.iter().map(|g| Some(g.as_slice())).collect();
+            let state = state(&groups);
+            let rebalanced = plan(&state);
+
+            let before: u64 = rebalanced.groups.iter().map(|g| g.before).sum();
+            let after = sizes(&rebalanced);
+            assert_eq!(after.iter().sum::<u64>(), before);
+            let average = before as f64 / after.len() as f64;
+            let gap = after.iter().max().unwrap() - after.iter().min().unwrap();
+            if gap as f64 <= average * TOLERANCE {
+                continue;
+            }
+            // not balanced, then no move from the biggest group to the
+            // smallest one would have made enough of a difference
+            let big = rebalanced.groups.iter().max_by_key(|g| g.after).unwrap().id;
+            let movable = state
+                .groups
+                .iter()
+                .flat_map(|g| &g.tablets)
+                .filter(|t| t.on_disk_bytes > 0)
+                .filter(|t| {
+                    let to = rebalanced.moves.iter().find(|m| m.predicate == t.predicate);
+                    to.map_or(t.group_id, |m| m.to) == big
+                });
+            for t in movable {
+                let gap_after = (gap as i128 - 2 * t.on_disk_bytes as i128).unsigned_abs() as u64;
+                assert!(
+                    gap.saturating_sub(gap_after) as f64 <= average * TOLERANCE,
+                    "{:?}",
+                    groups
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+// tls configuration for clusters with a private ca and/or client
+// certificates (mutual tls).
+// see: https://dgraph.io/docs/deploy/tls-configuration/
+
+use anyhow::{anyhow, Context, Result};
+use rustls::{
+    internal::pemfile, Certificate, ClientConfig, PrivateKey, RootCertStore, ServerCertVerified,
+    ServerCertVerifier, TLSError, WebPKIVerifier,
+};
+use std::{fs::File, io::BufReader, sync::Arc};
+use webpki::{DNSName, DNSNameRef};
+
+#[derive(Default, Clone)]
+pub struct TlsOptions {
+    // pem file with ca certificates to trust instead of the default roots
+    pub ca: Option<String>,
+    // pem files with client certificate (chain) and its private key
+    pub cert: Option<String>,
+    pub key: Option<String>,
+    // name to verify server certificate against, instead of url's host
+    pub server_name: Option<String>,
+}
+
+impl TlsOptions {
+    fn is_empty(&self) -> bool {
+        self.ca.is_none() && self.cert.is_none() && self.key.is_none() && self.server_name.is_none()
+    }
+}
+
+// `client_config` returns `None` if there's nothing to configure, so that
+// ureq's defaults are used.
+pub fn client_config(options: &TlsOptions) -> Result<Option<Arc<ClientConfig>>> {
+    if options.is_empty() {
+        return Ok(None);
+    }
+    let mut config = ClientConfig::new();
+    match &options.ca {
+        Some(path) => {
+            let (valid, _) = config
+                .root_store
+                .add_pem_file(&mut open(path)?)
+                .map_err(|_| anyhow!("could not parse {}", path))?;
+            if valid == 0 {
+                return Err(anyhow!("no valid ca certificates in {}", path));
+            }
+        }
+        None => config
+            .root_store
+            .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS),
+    }
+    match (&options.cert, &options.key) {
+        (Some(cert), Some(key)) => {
+            let chain = read_certs(cert)?;
+            let key = read_key(key)?;
+            config
+                .set_single_client_cert(chain, key)
+                .context("invalid client certificate or key")?;
+        }
+        (None, None) => {}
+        _ => return Err(anyhow!("--tls-cert and --tls-key must be used together")),
+    }
+    if let Some(name) = &options.server_name {
+        let name = DNSNameRef::try_from_ascii_str(name)
+            .map_err(|_| anyhow!("invalid server name {:?}", name))?
+            .to_owned();
+        config
+            .dangerous()
+            .set_certificate_verifier(Arc::new(ServerNameVerifier { name }));
+    }
+    Ok(Some(Arc::new(config)))
+}
+
+fn open(path: &str) -> Result<BufReader<File>> {
+    let file = File::open(path).with_context(|| format!("could not open {}", path))?;
+    Ok(BufReader::new(file))
+}
+
+fn read_certs(path: &str) -> Result<Vec<Certificate>> {
+    let certs =
+        pemfile::certs(&mut open(path)?).map_err(|_| anyhow!("could not parse {}", path))?;
+    if certs.is_empty() {
+        return Err(anyhow!("no certificates in {}", path));
+    }
+    Ok(certs)
+}
+
+// `read_key` reads the first pkcs8 or rsa private key.
+fn read_key(path: &str) -> Result<PrivateKey> {
+    let parse_error = || anyhow!("could not parse {}", path);
+    let mut keys = pemfile::pkcs8_private_keys(&mut open(path)?).map_err(|_| parse_error())?;
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut open(path)?).map_err(|_| parse_error())?;
+    }
+    keys.into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("no pkcs8 or rsa private key in {}", path))
+}
+
+// `ServerNameVerifier` does the usual verification, but against the given
+// name. useful when alphas are reached by ip or through a tunnel, and their
+// certificates are issued for internal names.
+struct ServerNameVerifier {
+    name: DNSName,
+}
+
+impl ServerCertVerifier for ServerNameVerifier {
+    fn verify_server_cert(
+        &self,
+        roots: &RootCertStore,
+        presented_certs: &[Certificate],
+        _dns_name: DNSNameRef,
+        ocsp_response: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        WebPKIVerifier::new().verify_server_cert(
+            roots,
+            presented_certs,
+            self.name.as_ref(),
+            ocsp_response,
+        )
+    }
+}
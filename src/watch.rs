@@ -0,0 +1,104 @@
+// `--watch` polls something over and over, redrawing it in the terminal and
+// keeping a history of what changed between polls.
+
+use anyhow::Result;
+use humantime::{format_duration, format_rfc3339_seconds};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    thread::sleep,
+    time::{Duration, Instant, SystemTime},
+};
+
+// rows stay highlighted for a while, so that a change is not missed between
+// redraws.
+const HIGHLIGHT_FOR: Duration = Duration::from_secs(30);
+// how many of the latest changes to show
+const HISTORY_SIZE: usize = 10;
+
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+
+// `Change` is something that happened to a row (for example a node) between
+// two polls.
+pub struct Change {
+    pub key: String,
+    pub description: String,
+}
+
+pub trait Snapshot {
+    // `changes` compares the snapshot to the previous one.
+    fn changes(&self, previous: &Self) -> Vec<Change>;
+
+    // `print_highlighted` prints the snapshot, highlighting rows with the
+    // given keys.
+    fn print_highlighted(&self, highlighted: &HashSet<String>);
+}
+
+// `watch` polls every `interval` until interrupted. errors don't stop it,
+// they are shown in place of the snapshot, so that it's possible to watch
+// the cluster come back.
+pub fn watch<S: Snapshot>(
+    title: &str,
+    interval: Duration,
+    mut poll: impl FnMut() -> Result<S>,
+) -> Result<i32> {
+    let mut previous: Option<S> = None;
+    let mut changed_at: HashMap<String, Instant> = HashMap::new();
+    let mut history: VecDeque<String> = VecDeque::new();
+    let mut last_error = String::new();
+    loop {
+        let result = poll();
+        let now = format_rfc3339_seconds(SystemTime::now());
+        let mut log = |line: String| {
+            history.push_back(format!("{}  {}", now, line));
+            if history.len() > HISTORY_SIZE {
+                history.pop_front();
+            }
+        };
+        match &result {
+            Ok(snapshot) => {
+                last_error.clear();
+                if let Some(previous) = &previous {
+                    for change in snapshot.changes(previous) {
+                        log(format!("{}: {}", &change.key, &change.description));
+                        changed_at.insert(change.key, Instant::now());
+                    }
+                }
+            }
+            Err(err) => {
+                let err = format!("{:#}", err);
+                // the same error on every poll is not news
+                if err != last_error {
+                    log(format!("error: {}", &err));
+                    last_error = err;
+                }
+            }
+        }
+
+        print!("{}", CLEAR_SCREEN);
+        println!("every {}: {}    {}", format_duration(interval), title, now);
+        println!();
+        match result {
+            Ok(snapshot) => {
+                changed_at.retain(|_, at| at.elapsed() < HIGHLIGHT_FOR);
+                snapshot.print_highlighted(&changed_at.keys().cloned().collect());
+                previous = Some(snapshot);
+            }
+            // keep the previous snapshot, to compare against it once dgraph
+            // is reachable again
+            Err(_) => println!("error: {}", &last_error),
+        }
+        if !history.is_empty() {
+            println!();
+            println!("changes:");
+            for line in &history {
+                println!("  {}", line);
+            }
+        }
+        sleep(interval);
+    }
+}
+
+// `highlight` makes the line stand out (bold yellow).
+pub fn highlight(line: &str) -> String {
+    format!("\x1b[1;33m{}\x1b[0m", line)
+}
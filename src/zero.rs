@@ -0,0 +1,111 @@
+// client for zero's http endpoints, for what alphas don't expose through the
+// admin endpoint.
+// see: https://dgraph.io/docs/deploy/dgraph-zero/
+
+use crate::{
+    dgraph::Dgraph,
+    output::Report,
+    state::{uint, State},
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+// the same as in `dgraph zero --port_offset 0`
+pub const DEFAULT_URL: &str = "localhost:6080";
+
+pub struct Zero {
+    client: Dgraph,
+}
+
+// `Lease` is what zero hands out in ranges.
+#[derive(Clone, Copy)]
+pub enum Lease {
+    Uids,
+    Timestamps,
+    NamespaceIds,
+}
+
+impl Lease {
+    fn as_str(self) -> &'static str {
+        match self {
+            Lease::Uids => "uids",
+            Lease::Timestamps => "timestamps",
+            Lease::NamespaceIds => "nsids",
+        }
+    }
+}
+
+pub fn parse_lease(s: &str) -> Result<Lease, String> {
+    match s {
+        "uids" => Ok(Lease::Uids),
+        "timestamps" => Ok(Lease::Timestamps),
+        "nsids" => Ok(Lease::NamespaceIds),
+        _ => Err(format!(
+            "unknown lease {:?}, expected uids, timestamps or nsids",
+            s
+        )),
+    }
+}
+
+// `Assigned` is the range of leased ids, both ends included.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Assigned {
+    #[serde(default, deserialize_with = "uint")]
+    pub start_id: u64,
+    #[serde(default, deserialize_with = "uint")]
+    pub end_id: u64,
+}
+
+impl Report for Assigned {
+    fn print_text(&self) {
+        println!("assigned {} to {}", self.start_id, self.end_id);
+    }
+}
+
+impl Zero {
+    // `new` constructs a client for zero, with the same options as the client
+    // for alpha, but with zero's auth header (`--zero-auth`).
+    pub fn new(dgraph: &Dgraph, url: &str) -> Result<Self> {
+        Ok(Self {
+            client: dgraph.with_url(url)?.without_credentials(),
+        })
+    }
+
+    pub fn client(&self) -> &Dgraph {
+        &self.client
+    }
+
+    pub fn state(&self) -> Result<State> {
+        self.client.get("state")
+    }
+
+    // `remove_node` removes the node from the group for good, its raft id
+    // can not be used again.
+    pub fn remove_node(&self, id: u64, group: u64) -> Result<String> {
+        let resp = self
+            .client
+            .get_mutating(&format!("removeNode?id={}&group={}", id, group))?;
+        Ok(resp.trim().to_string())
+    }
+
+    // `apply_license` applies an enterprise license, as issued by dgraph.
+    pub fn apply_license(&self, license: &str) -> Result<String> {
+        #[derive(Deserialize, Debug)]
+        struct Status {
+            message: String,
+        }
+        let resp = self.client.post_mutating("enterpriseLicense", license)?;
+        let status: Status = serde_json::from_str(&resp)
+            .with_context(|| format!("unexpected response: {:?}", resp))?;
+        Ok(status.message)
+    }
+
+    // `assign` leases the next `num` ids, so that zero never hands them out.
+    pub fn assign(&self, lease: Lease, num: u64) -> Result<Assigned> {
+        let resp =
+            self.client
+                .get_mutating(&format!("assign?what={}&num={}", lease.as_str(), num))?;
+        serde_json::from_str(&resp).with_context(|| format!("unexpected response: {:?}", resp))
+    }
+}